@@ -1,27 +1,135 @@
-use std::collections::HashSet;
-use std::ops::Add;
+use advent_of_code_2024::config::key_value_pairs;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, RangeInclusive};
 use std::{error::Error, num::ParseIntError};
 
 fn height_map_from_string(data: String) -> Result<HeightMap, ParseIntError> {
-    let heights: Vec<Vec<i32>> = data
+    let heights: Vec<Vec<Option<i32>>> = data
         .lines()
         .map(|row| {
             row.chars()
-                .map(|c| c.to_string().parse::<i32>())
-                .collect::<Result<Vec<i32>, ParseIntError>>()
+                .map(|c| match c {
+                    '.' => Ok(None),
+                    _ => c.to_string().parse::<i32>().map(Some),
+                })
+                .collect::<Result<Vec<Option<i32>>, ParseIntError>>()
         })
         .rev()
-        .collect::<Result<Vec<Vec<i32>>, ParseIntError>>()?;
+        .collect::<Result<Vec<Vec<Option<i32>>>, ParseIntError>>()?;
 
-    Ok(HeightMap { heights })
+    Ok(HeightMap {
+        heights,
+        rules: TrailRules::default(),
+    })
+}
+
+// The config has one "key = value" per line with the keys min_step, max_step,
+// neighborhood (4 or 8), start_height, end_height and allow_descending.
+fn trail_rules_from_config(config: &str) -> Result<TrailRules, Box<dyn Error>> {
+    let mut rules = TrailRules::default();
+    let (mut min_step, mut max_step) = (1, 1);
+
+    for (key, value) in key_value_pairs(config)? {
+        match key {
+            "min_step" => min_step = value.parse::<i32>()?,
+            "max_step" => max_step = value.parse::<i32>()?,
+            "neighborhood" => {
+                rules.neighborhood = match value {
+                    "4" => Neighborhood::Four,
+                    "8" => Neighborhood::Eight,
+                    _ => return Err(format!("Neighborhood must be 4 or 8, got {value}").into()),
+                }
+            }
+            "start_height" => rules.start_height = value.parse::<i32>()?,
+            "end_height" => rules.end_height = value.parse::<i32>()?,
+            "allow_descending" => rules.allow_descending = value.parse::<bool>()?,
+            _ => return Err(format!("Unknown trail rule \"{key}\"").into()),
+        }
+    }
+
+    rules.step_deltas = min_step..=max_step;
+    Ok(rules)
 }
 
 fn import_data() -> Result<String, Box<dyn Error>> {
     Ok(std::fs::read_to_string("data/day10.txt")?)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Neighborhood {
+    Four,
+    Eight,
+}
+
+#[derive(Debug, Clone)]
+struct TrailRules {
+    step_deltas: RangeInclusive<i32>,
+    neighborhood: Neighborhood,
+    start_height: i32,
+    end_height: i32,
+    allow_descending: bool,
+}
+
+impl Default for TrailRules {
+    fn default() -> TrailRules {
+        TrailRules {
+            step_deltas: 1..=1,
+            neighborhood: Neighborhood::Four,
+            start_height: 0,
+            end_height: 9,
+            allow_descending: false,
+        }
+    }
+}
+
+impl TrailRules {
+    fn allows_step(&self, delta: i32) -> bool {
+        self.step_deltas.contains(&delta)
+            || (self.allow_descending && self.step_deltas.contains(&-delta))
+    }
+
+    fn directions(&self) -> Vec<Position> {
+        let mut directions = vec![
+            Position { x: 1, y: 0 },
+            Position { x: 0, y: -1 },
+            Position { x: -1, y: 0 },
+            Position { x: 0, y: 1 },
+        ];
+        if self.neighborhood == Neighborhood::Eight {
+            directions.extend([
+                Position { x: 1, y: 1 },
+                Position { x: 1, y: -1 },
+                Position { x: -1, y: -1 },
+                Position { x: -1, y: 1 },
+            ]);
+        }
+        directions
+    }
+
+    // Some(1) when every step climbs and Some(-1) when every step descends, so trails can
+    // never loop back on themselves. None when a trail could revisit a height.
+    fn height_direction(&self) -> Option<i32> {
+        let (lowest, highest) = (*self.step_deltas.start(), *self.step_deltas.end());
+        match (self.allow_descending, lowest > 0, highest < 0) {
+            (false, true, _) => Some(1),
+            (false, _, true) => Some(-1),
+            _ if lowest > highest => Some(1),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, a: Position, b: Position) -> i32 {
+        let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+        match self.neighborhood {
+            Neighborhood::Four => dx + dy,
+            Neighborhood::Eight => dx.max(dy),
+        }
+    }
+}
+
 struct HeightMap {
-    heights: Vec<Vec<i32>>,
+    heights: Vec<Vec<Option<i32>>>,
+    rules: TrailRules,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -42,39 +150,51 @@ impl Add for Position {
 }
 
 impl HeightMap {
-    fn get_trail_heads(&self) -> Vec<Position> {
-        let mut trail_heads: Vec<Position> = Vec::new();
+    fn with_rules(self, rules: TrailRules) -> HeightMap {
+        HeightMap { rules, ..self }
+    }
+
+    fn positions_with_height(&self, wanted_height: i32) -> Vec<Position> {
+        let mut positions: Vec<Position> = Vec::new();
         for (y, row) in self.heights.iter().enumerate() {
             for (x, height) in row.iter().enumerate() {
-                if *height == 0 {
-                    trail_heads.push(Position {
+                if *height == Some(wanted_height) {
+                    positions.push(Position {
                         x: x as i32,
                         y: y as i32,
                     });
                 }
             }
         }
-        trail_heads
+        positions
     }
 
-    fn step_one_up_from_position(&self, position: Position) -> Vec<Position> {
-        let possible_directions = [
-            Position { x: 1, y: 0 },
-            Position { x: 0, y: -1 },
-            Position { x: -1, y: 0 },
-            Position { x: 0, y: 1 },
-        ];
-        let old_height = self.get_height(position);
+    fn get_trail_heads(&self) -> Vec<Position> {
+        self.positions_with_height(self.rules.start_height)
+    }
+
+    fn get_peaks(&self) -> Vec<Position> {
+        self.positions_with_height(self.rules.end_height)
+    }
+
+    fn valid_steps_from_position(&self, position: Position) -> Vec<Position> {
+        let Some(old_height) = self.get_height(position) else {
+            return Vec::new();
+        };
 
-        possible_directions
+        self.rules
+            .directions()
             .iter()
             .map(|&p| position + p)
             .filter(|&p| self.is_position_within_map(p))
-            .filter(|&p| self.get_height(p) == old_height + 1)
+            .filter(|&p| {
+                self.get_height(p)
+                    .is_some_and(|height| self.rules.allows_step(height - old_height))
+            })
             .collect()
     }
 
-    fn get_height(&self, position: Position) -> i32 {
+    fn get_height(&self, position: Position) -> Option<i32> {
         self.heights[position.y as usize][position.x as usize]
     }
 
@@ -86,42 +206,492 @@ impl HeightMap {
         x >= 0 && x < length_x && y >= 0 && y < length_y
     }
 
-    fn number_of_ways_to_a_top(&self, position: Position) -> i32 {
-        match self.get_height(position) {
-            9 => 1,
-            _ => self
-                .step_one_up_from_position(position)
-                .iter()
-                .map(|&p| self.number_of_ways_to_a_top(p))
-                .sum(),
+    fn trails(&self, trail_head: Option<Position>, peak: Option<Position>) -> Trails<'_> {
+        let trail_heads = match trail_head {
+            Some(trail_head) if self.is_position_within_map(trail_head) => vec![trail_head],
+            Some(_) => Vec::new(),
+            None => self.get_trail_heads(),
+        };
+
+        Trails {
+            height_map: self,
+            peak,
+            partial_trails: trail_heads
+                .into_iter()
+                .filter(|&trail_head| self.get_height(trail_head) == Some(self.rules.start_height))
+                .rev()
+                .map(|trail_head| vec![trail_head])
+                .collect(),
         }
     }
-}
 
-fn part1(height_map: &HeightMap) -> Result<i32, Box<dyn Error>> {
-    let mut score = 0;
+    fn render_trails(&self, trails: &[Vec<Position>]) -> String {
+        let on_trail: HashSet<Position> = trails.iter().flatten().copied().collect();
 
-    for trail_heads in height_map.get_trail_heads() {
-        let mut current_points = HashSet::new();
-        current_points.insert(trail_heads);
+        self.heights
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(y, row)| {
+                let mut line: String = (0..row.len())
+                    .map(|x| {
+                        let position = Position {
+                            x: x as i32,
+                            y: y as i32,
+                        };
+                        match (on_trail.contains(&position), row[x]) {
+                            (true, Some(height)) => {
+                                char::from_digit(height as u32, 10).unwrap_or('?')
+                            }
+                            _ => '.',
+                        }
+                    })
+                    .collect();
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
 
-        for _ in 0..9 {
-            current_points = current_points
+    fn trails_to_svg(&self, trails: &[Vec<Position>]) -> String {
+        const CELL_SIZE: i32 = 10;
+        let length_y = self.heights.len() as i32;
+        let length_x = self.heights.first().map_or(0, |row| row.len()) as i32;
+        // SVG has y pointing down, so the rows are flipped back to the order of the input.
+        let center = |position: &Position| {
+            (
+                position.x * CELL_SIZE + CELL_SIZE / 2,
+                (length_y - 1 - position.y) * CELL_SIZE + CELL_SIZE / 2,
+            )
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">\n",
+            length_x * CELL_SIZE,
+            length_y * CELL_SIZE
+        );
+        for (y, row) in self.heights.iter().enumerate() {
+            for (x, &height) in row.iter().enumerate() {
+                // Impassable cells are drawn black.
+                let shade = height.map_or(0, |height| 40 + 20 * height.clamp(0, 9));
+                svg.push_str(&format!(
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{CELL_SIZE}\" height=\"{CELL_SIZE}\" fill=\"rgb({shade},{shade},{shade})\"/>\n",
+                    x as i32 * CELL_SIZE,
+                    (length_y - 1 - y as i32) * CELL_SIZE
+                ));
+            }
+        }
+        for trail in trails {
+            let points: Vec<String> = trail
                 .iter()
-                .flat_map(|&p| height_map.step_one_up_from_position(p))
+                .map(center)
+                .map(|(x, y)| format!("{x},{y}"))
                 .collect();
+            svg.push_str(&format!(
+                "  <polyline points=\"{}\" fill=\"none\" stroke=\"red\" stroke-width=\"2\"/>\n",
+                points.join(" ")
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn to_graph(&self) -> TrailGraph {
+        let mut graph = TrailGraph {
+            nodes: Vec::new(),
+            heights: Vec::new(),
+            edges: Vec::new(),
+        };
+        let mut node_indices: HashMap<Position, usize> = HashMap::new();
+        for (y, row) in self.heights.iter().enumerate() {
+            for (x, height) in row.iter().enumerate() {
+                if let Some(height) = height {
+                    let position = Position {
+                        x: x as i32,
+                        y: y as i32,
+                    };
+                    node_indices.insert(position, graph.nodes.len());
+                    graph.nodes.push(position);
+                    graph.heights.push(*height);
+                }
+            }
+        }
+
+        graph.edges = graph
+            .nodes
+            .iter()
+            .map(|&position| {
+                self.valid_steps_from_position(position)
+                    .iter()
+                    .map(|next| node_indices[next])
+                    .collect()
+            })
+            .collect();
+        graph
+    }
+
+    fn analyse_graph(&self) -> GraphAnalysis {
+        let graph = self.to_graph();
+        let trail_heads = self.get_trail_heads();
+        let peaks = self.get_peaks();
+        let node_indices: HashMap<Position, usize> = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, &node)| (node, index))
+            .collect();
+
+        let mut reached_by_any_trail_head = vec![false; graph.nodes.len()];
+        let mut peak_popularity: HashMap<Position, usize> =
+            peaks.iter().map(|&peak| (peak, 0)).collect();
+        let mut reachable_per_trail_head = Vec::new();
+        for trail_head in trail_heads {
+            let reachable = graph.reachable_from(node_indices[&trail_head]);
+            for &node in &reachable {
+                reached_by_any_trail_head[node] = true;
+                if let Some(popularity) = peak_popularity.get_mut(&graph.nodes[node]) {
+                    *popularity += 1;
+                }
+            }
+            reachable_per_trail_head.push((trail_head, reachable.len()));
+        }
+
+        GraphAnalysis {
+            reachable_per_trail_head,
+            peak_popularity: peaks
+                .iter()
+                .map(|peak| (*peak, peak_popularity[peak]))
+                .collect(),
+            unreachable_cells: graph
+                .nodes
+                .iter()
+                .zip(reached_by_any_trail_head)
+                .filter(|(_, reached)| !reached)
+                .map(|(&node, _)| node)
+                .collect(),
+        }
+    }
+
+    // When every step moves the height the same way the cells are walked from the trail ends
+    // backwards, so every cell combines the finished results of the cells it can step to
+    // instead of exploring its trails again. Otherwise the trails are enumerated per trail head.
+    fn trail_summary(&self) -> TrailSummary {
+        let peaks = self.get_peaks();
+        let peak_indices: HashMap<Position, usize> = peaks
+            .iter()
+            .enumerate()
+            .map(|(index, &peak)| (peak, index))
+            .collect();
+
+        let mut summary = TrailSummary {
+            number_of_trails: self.heights.iter().map(|row| vec![0; row.len()]).collect(),
+            reachable_peaks: self
+                .heights
+                .iter()
+                .map(|row| vec![PeakSet::new(peaks.len()); row.len()])
+                .collect(),
+        };
+
+        let Some(height_direction) = self.rules.height_direction() else {
+            for trail_head in self.get_trail_heads() {
+                let (x, y) = (trail_head.x as usize, trail_head.y as usize);
+                for trail in self.trails(Some(trail_head), None) {
+                    summary.number_of_trails[y][x] += 1;
+                    if let Some(peak) = trail.last() {
+                        summary.reachable_peaks[y][x].insert(peak_indices[peak]);
+                    }
+                }
+            }
+            return summary;
+        };
+
+        let mut positions_by_height: Vec<(i32, Position)> = Vec::new();
+        for (y, row) in self.heights.iter().enumerate() {
+            for (x, height) in row.iter().enumerate() {
+                if let Some(height) = height {
+                    let position = Position {
+                        x: x as i32,
+                        y: y as i32,
+                    };
+                    positions_by_height.push((*height * height_direction, position));
+                }
+            }
+        }
+        positions_by_height.sort_by_key(|&(height, _)| std::cmp::Reverse(height));
+
+        for (_, position) in positions_by_height {
+            let (x, y) = (position.x as usize, position.y as usize);
+            if let Some(&peak_index) = peak_indices.get(&position) {
+                summary.number_of_trails[y][x] = 1;
+                summary.reachable_peaks[y][x].insert(peak_index);
+                continue;
+            }
+
+            let mut number_of_trails = 0;
+            let mut reachable_peaks = PeakSet::new(peaks.len());
+            for next in self.valid_steps_from_position(position) {
+                number_of_trails += summary.number_of_trails[next.y as usize][next.x as usize];
+                reachable_peaks
+                    .union_with(&summary.reachable_peaks[next.y as usize][next.x as usize]);
+            }
+            summary.number_of_trails[y][x] = number_of_trails;
+            summary.reachable_peaks[y][x] = reachable_peaks;
+        }
+
+        summary
+    }
+}
+
+struct Trails<'a> {
+    height_map: &'a HeightMap,
+    peak: Option<Position>,
+    partial_trails: Vec<Vec<Position>>,
+}
+
+impl Iterator for Trails<'_> {
+    type Item = Vec<Position>;
+
+    fn next(&mut self) -> Option<Vec<Position>> {
+        let rules = &self.height_map.rules;
+        let height_direction = rules.height_direction();
+        let smallest_step = rules
+            .step_deltas
+            .start()
+            .abs()
+            .min(rules.step_deltas.end().abs())
+            .max(1);
+
+        while let Some(trail) = self.partial_trails.pop() {
+            let last = *trail.last()?;
+            let height = self.height_map.get_height(last)?;
+            if height == rules.end_height {
+                if self.peak.is_none_or(|peak| peak == last) {
+                    return Some(trail);
+                }
+                continue;
+            }
+
+            // With every step moving the height the same way, a peak further away than the
+            // remaining number of steps is out of reach.
+            if let (Some(peak), Some(_)) = (self.peak, height_direction) {
+                let remaining_steps = (rules.end_height - height).abs() / smallest_step;
+                if rules.distance(peak, last) > remaining_steps {
+                    continue;
+                }
+            }
+
+            for next in self
+                .height_map
+                .valid_steps_from_position(last)
+                .into_iter()
+                .rev()
+            {
+                if height_direction.is_none() && trail.contains(&next) {
+                    continue;
+                }
+                let mut extended_trail = trail.clone();
+                extended_trail.push(next);
+                self.partial_trails.push(extended_trail);
+            }
         }
-        score += current_points.len() as i32
+        None
     }
-    Ok(score)
 }
 
-fn part2(height_map: &HeightMap) -> Result<i32, Box<dyn Error>> {
-    let mut score = 0;
-    for trail_heads in height_map.get_trail_heads() {
-        score += height_map.number_of_ways_to_a_top(trail_heads);
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PeakSet {
+    bits: Vec<u64>,
+}
+
+impl PeakSet {
+    fn new(number_of_peaks: usize) -> PeakSet {
+        PeakSet {
+            bits: vec![0; number_of_peaks.div_ceil(64)],
+        }
+    }
+
+    fn insert(&mut self, peak_index: usize) {
+        self.bits[peak_index / 64] |= 1 << (peak_index % 64);
+    }
+
+    fn union_with(&mut self, other: &PeakSet) {
+        for (bits, other_bits) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *bits |= other_bits;
+        }
     }
-    Ok(score)
+
+    fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|bits| bits.count_ones() as usize)
+            .sum()
+    }
+}
+
+struct TrailSummary {
+    number_of_trails: Vec<Vec<i64>>,
+    reachable_peaks: Vec<Vec<PeakSet>>,
+}
+
+// Nodes are the passable cells, edges[i] lists the nodes a trail can step to from node i.
+struct TrailGraph {
+    nodes: Vec<Position>,
+    heights: Vec<i32>,
+    edges: Vec<Vec<usize>>,
+}
+
+impl TrailGraph {
+    fn node_name(&self, node: usize) -> String {
+        format!("x{}_y{}", self.nodes[node].x, self.nodes[node].y)
+    }
+
+    // Includes the start node itself.
+    fn reachable_from(&self, start: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![start];
+        let mut reachable = Vec::new();
+        visited[start] = true;
+        while let Some(node) = stack.pop() {
+            reachable.push(node);
+            for &next in &self.edges[node] {
+                if !visited[next] {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        reachable
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph height_map {\n");
+        for (node, height) in self.heights.iter().enumerate() {
+            dot.push_str(&format!(
+                "  {} [label=\"{}\"];\n",
+                self.node_name(node),
+                height
+            ));
+        }
+        for (from, targets) in self.edges.iter().enumerate() {
+            for &to in targets {
+                dot.push_str(&format!(
+                    "  {} -> {};\n",
+                    self.node_name(from),
+                    self.node_name(to)
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_graphml(&self) -> String {
+        let mut graphml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"x\" for=\"node\" attr.name=\"x\" attr.type=\"int\"/>\n",
+            "  <key id=\"y\" for=\"node\" attr.name=\"y\" attr.type=\"int\"/>\n",
+            "  <key id=\"height\" for=\"node\" attr.name=\"height\" attr.type=\"int\"/>\n",
+            "  <graph id=\"height_map\" edgedefault=\"directed\">\n",
+        ));
+        for (node, (position, height)) in self.nodes.iter().zip(&self.heights).enumerate() {
+            graphml.push_str(&format!(
+                "    <node id=\"{}\"><data key=\"x\">{}</data><data key=\"y\">{}</data><data key=\"height\">{}</data></node>\n",
+                self.node_name(node),
+                position.x,
+                position.y,
+                height
+            ));
+        }
+        for (from, targets) in self.edges.iter().enumerate() {
+            for &to in targets {
+                graphml.push_str(&format!(
+                    "    <edge source=\"{}\" target=\"{}\"/>\n",
+                    self.node_name(from),
+                    self.node_name(to)
+                ));
+            }
+        }
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+}
+
+struct GraphAnalysis {
+    // Number of cells reachable from each trail head, the trail head included.
+    reachable_per_trail_head: Vec<(Position, usize)>,
+    // Number of trail heads from which each peak can be reached.
+    peak_popularity: Vec<(Position, usize)>,
+    // Passable cells that no trail head can reach.
+    unreachable_cells: Vec<Position>,
+}
+
+fn print_graph_analysis(analysis: &GraphAnalysis) {
+    println!("Reachable cells per trail head:");
+    for (trail_head, reachable) in &analysis.reachable_per_trail_head {
+        println!("  ({}, {}): {reachable}", trail_head.x, trail_head.y);
+    }
+    println!("Trail heads reaching each peak:");
+    for (peak, popularity) in &analysis.peak_popularity {
+        println!("  ({}, {}): {popularity}", peak.x, peak.y);
+    }
+    println!(
+        "{} passable cells are unreachable from every trail head",
+        analysis.unreachable_cells.len()
+    );
+}
+
+fn part1(height_map: &HeightMap) -> Result<i64, Box<dyn Error>> {
+    let summary = height_map.trail_summary();
+
+    let score = height_map
+        .get_trail_heads()
+        .iter()
+        .map(|trail_head| {
+            summary.reachable_peaks[trail_head.y as usize][trail_head.x as usize].len()
+        })
+        .sum::<usize>();
+    Ok(i64::try_from(score)?)
+}
+
+fn part2(height_map: &HeightMap) -> Result<i64, Box<dyn Error>> {
+    let summary = height_map.trail_summary();
+
+    let rating = height_map
+        .get_trail_heads()
+        .iter()
+        .map(|trail_head| summary.number_of_trails[trail_head.y as usize][trail_head.x as usize])
+        .sum::<i64>();
+    Ok(rating)
+}
+
+fn print_trails(height_map: &HeightMap, options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let mut as_svg = false;
+    let mut trail_head = None;
+    let mut peak = None;
+    let mut remaining = options.iter().copied();
+    while let Some(argument) = remaining.next() {
+        let mut parse_position = || -> Result<Position, Box<dyn Error>> {
+            let x = remaining.next().ok_or("Expected x")?.parse::<i32>()?;
+            let y = remaining.next().ok_or("Expected y")?.parse::<i32>()?;
+            Ok(Position { x, y })
+        };
+        match argument {
+            "svg" => as_svg = true,
+            "from" => trail_head = Some(parse_position()?),
+            "to" => peak = Some(parse_position()?),
+            _ => return Err(format!("Unknown argument {argument}").into()),
+        }
+    }
+
+    let trails: Vec<Vec<Position>> = height_map.trails(trail_head, peak).collect();
+    if as_svg {
+        print!("{}", height_map.trails_to_svg(&trails));
+    } else {
+        print!("{}", height_map.render_trails(&trails));
+        println!("{} trails", trails.len());
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -129,6 +699,43 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let height_map = height_map_from_string(data)?;
 
+    // "rules PATH" scores with the trail rules from a config file. "graph [dot|graphml]"
+    // analyses or exports the trail graph. "trails [svg] [from x y] [to x y]" prints the
+    // matching trails, with y counted from the bottom row of the map.
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    match arguments
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => {}
+        ["rules", config_path] => {
+            let rules = trail_rules_from_config(&std::fs::read_to_string(config_path)?)?;
+            let height_map = height_map.with_rules(rules);
+            println!("Score with custom rules is: {}", part1(&height_map)?);
+            println!("Rating with custom rules is: {}", part2(&height_map)?);
+            return Ok(());
+        }
+        ["graph"] => {
+            print_graph_analysis(&height_map.analyse_graph());
+            return Ok(());
+        }
+        ["graph", "dot"] => {
+            print!("{}", height_map.to_graph().to_dot());
+            return Ok(());
+        }
+        ["graph", "graphml"] => {
+            print!("{}", height_map.to_graph().to_graphml());
+            return Ok(());
+        }
+        ["trails", options @ ..] => {
+            print_trails(&height_map, options)?;
+            return Ok(());
+        }
+        _ => return Err(format!("Unknown arguments {arguments:?}").into()),
+    }
+
     let result_part1 = part1(&height_map)?;
     let result_part2 = part2(&height_map)?;
 
@@ -181,4 +788,185 @@ mod tests {
         assert!(result == 81);
         Ok(())
     }
+
+    #[test]
+    fn test_more_peaks_than_fit_in_one_word() -> Result<(), Box<dyn Error>> {
+        let data_as_string = (0..10)
+            .rev()
+            .map(|height| height.to_string().repeat(70))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let height_map = height_map_from_string(data_as_string)?;
+        let summary = height_map.trail_summary();
+
+        assert_eq!(summary.reachable_peaks[0][0].bits.len(), 2);
+        assert_eq!(part1(&height_map)?, 70);
+        assert_eq!(part2(&height_map)?, 70);
+        Ok(())
+    }
+
+    #[test]
+    fn test_enumerated_trails_match_ratings() -> Result<(), Box<dyn Error>> {
+        let height_map = height_map_from_string(get_test_data())?;
+
+        assert_eq!(height_map.trails(None, None).count(), 81);
+
+        let trail_head = Position { x: 2, y: 7 };
+        let from_trail_head: Vec<Vec<Position>> =
+            height_map.trails(Some(trail_head), None).collect();
+        assert_eq!(from_trail_head.len(), 20);
+        for trail in from_trail_head.iter() {
+            assert_eq!(trail.len(), 10);
+            assert_eq!(trail[0], trail_head);
+            for (step, position) in trail.iter().enumerate() {
+                assert_eq!(height_map.get_height(*position), Some(step as i32));
+            }
+        }
+
+        let peak = from_trail_head[0][9];
+        let to_peak = height_map.trails(Some(trail_head), Some(peak)).count();
+        assert!(to_peak > 0 && to_peak < 20);
+        assert_eq!(
+            to_peak,
+            from_trail_head
+                .iter()
+                .filter(|trail| trail[9] == peak)
+                .count()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_trails() -> Result<(), Box<dyn Error>> {
+        let height_map = height_map_from_string(
+            "0123
+            1234
+            8765
+            9876"
+                .replace("    ", "")
+                .to_string(),
+        )?;
+
+        let trails: Vec<Vec<Position>> = height_map.trails(None, None).collect();
+
+        assert_eq!(trails.len(), 16);
+        assert_eq!(
+            height_map.render_trails(&trails[..1]),
+            "0123\n...4\n...5\n9876\n"
+        );
+        assert_eq!(
+            height_map
+                .trails_to_svg(&trails)
+                .matches("<polyline")
+                .count(),
+            16
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_impassable_cells() -> Result<(), Box<dyn Error>> {
+        let score_example = "..90..9
+        ...1.98
+        ...2..7
+        6543456
+        765.987
+        876....
+        987...."
+            .replace("    ", "")
+            .to_string();
+        let rating_example = ".....0.
+        ..4321.
+        ..5..2.
+        ..6543.
+        ..7..4.
+        ..8765.
+        ..9...."
+            .replace("    ", "")
+            .to_string();
+
+        assert_eq!(part1(&height_map_from_string(score_example)?)?, 4);
+        assert_eq!(part2(&height_map_from_string(rating_example)?)?, 3);
+        assert!(height_map_from_string("01x".to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_trail_rules() -> Result<(), Box<dyn Error>> {
+        let descending = trail_rules_from_config(
+            "start_height = 9
+            end_height = 0
+            min_step = -1
+            max_step = -1",
+        )?;
+        let height_map = height_map_from_string(get_test_data())?.with_rules(descending);
+        assert_eq!(part1(&height_map)?, 36);
+        assert_eq!(part2(&height_map)?, 81);
+        assert_eq!(height_map.trails(None, None).count(), 81);
+
+        let up_and_down = trail_rules_from_config("allow_descending = true")?;
+        let height_map = height_map_from_string("01232\n....3\n....4\n98765".to_string())?
+            .with_rules(up_and_down);
+        assert_eq!(part2(&height_map)?, 1);
+        assert_eq!(
+            part2(&height_map_from_string(
+                "01232\n....3\n....4\n98765".to_string()
+            )?)?,
+            0
+        );
+
+        let diagonal = trail_rules_from_config("neighborhood = 8")?;
+        let height_map = height_map_from_string(get_test_data())?.with_rules(diagonal);
+        assert!(part2(&height_map)? > 81);
+        assert_eq!(
+            part2(&height_map)?,
+            height_map.trails(None, None).count() as i64
+        );
+
+        let flat_steps = trail_rules_from_config("min_step = 0")?;
+        let height_map = height_map_from_string("00123456789".to_string())?.with_rules(flat_steps);
+        assert_eq!(part1(&height_map)?, 2);
+        assert_eq!(part2(&height_map)?, 2);
+
+        assert!(trail_rules_from_config("neighborhood = 6").is_err());
+        assert!(trail_rules_from_config("speed = 3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_analysis() -> Result<(), Box<dyn Error>> {
+        // Rows are listed top-down but y counts from the bottom row.
+        let height_map = height_map_from_string("0123\n.654\n0789\n9..9".to_string())?;
+        let graph = height_map.to_graph();
+        assert_eq!(graph.nodes.len(), 13);
+        assert_eq!(graph.edges.iter().map(Vec::len).sum::<usize>(), 9);
+
+        let analysis = height_map.analyse_graph();
+        assert_eq!(
+            analysis.reachable_per_trail_head,
+            vec![(Position { x: 0, y: 1 }, 1), (Position { x: 0, y: 3 }, 10)]
+        );
+        assert_eq!(
+            analysis.peak_popularity,
+            vec![
+                (Position { x: 0, y: 0 }, 0),
+                (Position { x: 3, y: 0 }, 0),
+                (Position { x: 3, y: 1 }, 1)
+            ]
+        );
+        assert_eq!(
+            analysis.unreachable_cells,
+            vec![Position { x: 0, y: 0 }, Position { x: 3, y: 0 }]
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("  x0_y3 -> x1_y3;\n"));
+        assert_eq!(dot.matches(" -> ").count(), 9);
+        assert!(dot.contains("  x3_y1 [label=\"9\"];\n"));
+        let graphml = graph.to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), 13);
+        assert!(graphml.contains("<edge source=\"x2_y1\" target=\"x3_y1\"/>"));
+        Ok(())
+    }
 }
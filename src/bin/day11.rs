@@ -1,11 +1,14 @@
-use cached::cached;
+use advent_of_code_2024::digits::{number_of_digits, split_at_digit};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::AddAssign;
 use std::{error::Error, num::ParseIntError};
 
-fn stone_numbers_from_string(data: String) -> Result<Vec<i64>, ParseIntError> {
-    let stone_numbers: Vec<i64> = data
-        .split(" ")
-        .map(|s| s.parse::<i64>())
-        .collect::<Result<Vec<i64>, ParseIntError>>()?;
+fn stone_numbers_from_string(data: String) -> Result<Vec<u64>, ParseIntError> {
+    let stone_numbers: Vec<u64> = data
+        .split_whitespace()
+        .map(|s| s.parse::<u64>())
+        .collect::<Result<Vec<u64>, ParseIntError>>()?;
 
     Ok(stone_numbers)
 }
@@ -14,40 +17,410 @@ fn import_data() -> Result<String, Box<dyn Error>> {
     Ok(std::fs::read_to_string("data/day11.txt")?)
 }
 
-cached! {
-    FIB: cached::UnboundCache<(Vec<i64>, i32), i64> = cached::UnboundCache::new();
-    fn number_of_stones_after_blinks(stone_numbers: Vec<i64>, remaining_blinks: i32) -> i64 = {
-        if remaining_blinks == 0 {
-            return stone_numbers.len() as i64
+const LIMB_BASE: u64 = 1_000_000_000_000_000_000;
+
+// Unbounded stone count stored in base 10^18 limbs, least significant limb first.
+// The number of stones grows by about half every blink, so u128 runs out after roughly
+// 200 blinks.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct StoneCount {
+    limbs: Vec<u64>,
+}
+
+impl StoneCount {
+    fn from_u64(value: u64) -> StoneCount {
+        let mut count = StoneCount::default();
+        count += &StoneCount {
+            limbs: vec![value % LIMB_BASE, value / LIMB_BASE],
+        };
+        count
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.limbs
+            .iter()
+            .rev()
+            .fold(0.0, |total, &limb| total * LIMB_BASE as f64 + limb as f64)
+    }
+
+    fn to_u128(&self) -> Option<u128> {
+        self.limbs.iter().rev().try_fold(0_u128, |total, &limb| {
+            total
+                .checked_mul(LIMB_BASE as u128)?
+                .checked_add(limb as u128)
+        })
+    }
+}
+
+impl AddAssign<&StoneCount> for StoneCount {
+    fn add_assign(&mut self, other: &StoneCount) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        let mut carry = 0;
+        for (index, limb) in self.limbs.iter_mut().enumerate() {
+            let sum = *limb + other.limbs.get(index).copied().unwrap_or(0) + carry;
+            *limb = sum % LIMB_BASE;
+            carry = sum / LIMB_BASE;
         }
+        if carry > 0 {
+            self.limbs.push(carry);
+        }
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
 
-        let mut number_of_stones = 0;
-        for stone_num in stone_numbers {
-            match stone_num {
-                0 => {
-                    number_of_stones += number_of_stones_after_blinks(vec![1], remaining_blinks-1);
-                },
-                _ if stone_num.to_string().len() % 2 == 0 => {
-                    let half_character_length = (stone_num.to_string().len() as i64) / 2;
-                    let number_1 = stone_num / 10_i64.pow(half_character_length as u32);
-                    let number_2 = stone_num - number_1 * 10_i64.pow(half_character_length as u32);
-                    number_of_stones += number_of_stones_after_blinks(vec![number_1, number_2], remaining_blinks - 1);
-                },
-                _ => {
-                    number_of_stones += number_of_stones_after_blinks(vec![2024 * stone_num], remaining_blinks - 1);
+impl fmt::Display for StoneCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.limbs.split_last() {
+            None => write!(f, "0"),
+            Some((most_significant, rest)) => {
+                write!(f, "{most_significant}")?;
+                for limb in rest.iter().rev() {
+                    write!(f, "{limb:018}")?;
                 }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StoneCondition {
+    Equals(u64),
+    NotEquals(u64),
+    LessThan(u64),
+    AtLeast(u64),
+    DigitsMultipleOf(u32),
+    DigitsNotMultipleOf(u32),
+}
+
+impl StoneCondition {
+    fn matches(&self, stone_num: u64) -> bool {
+        match *self {
+            StoneCondition::Equals(value) => stone_num == value,
+            StoneCondition::NotEquals(value) => stone_num != value,
+            StoneCondition::LessThan(value) => stone_num < value,
+            StoneCondition::AtLeast(value) => stone_num >= value,
+            StoneCondition::DigitsMultipleOf(k) => number_of_digits(stone_num).is_multiple_of(k),
+            StoneCondition::DigitsNotMultipleOf(k) => {
+                !number_of_digits(stone_num).is_multiple_of(k)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StoneAction {
+    Replace(u64),
+    Multiply(u64),
+    Add(u64),
+    Split(u32),
+    DigitSum,
+}
+
+impl StoneAction {
+    fn apply(&self, stone_num: u64) -> Option<Vec<u64>> {
+        match *self {
+            StoneAction::Replace(value) => Some(vec![value]),
+            StoneAction::Multiply(factor) => Some(vec![stone_num.checked_mul(factor)?]),
+            StoneAction::Add(term) => Some(vec![stone_num.checked_add(term)?]),
+            StoneAction::Split(parts) => {
+                let digits = number_of_digits(stone_num);
+                if !digits.is_multiple_of(parts) {
+                    return None;
+                }
+                let part_length = digits / parts;
+                let mut remaining = stone_num;
+                let mut split_stones: Vec<u64> = Vec::new();
+                for part in 1..parts {
+                    let (left, right) = split_at_digit(remaining, digits - part * part_length)?;
+                    remaining = left;
+                    split_stones.push(right);
+                }
+                split_stones.push(remaining);
+                split_stones.reverse();
+                Some(split_stones)
+            }
+            StoneAction::DigitSum => {
+                let mut remaining = stone_num;
+                let mut digit_sum = 0;
+                while remaining > 0 {
+                    digit_sum += remaining % 10;
+                    remaining /= 10;
+                }
+                Some(vec![digit_sum])
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoneRule {
+    conditions: Vec<StoneCondition>,
+    action: StoneAction,
+    line_number: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoneRules {
+    rules: Vec<StoneRule>,
+}
+
+const DEFAULT_STONE_RULES: &str = "equals 0 -> replace 1
+digits_multiple_of 2 -> split 2
+digits_not_multiple_of 2 and not_equals 0 -> multiply 2024";
+
+impl Default for StoneRules {
+    fn default() -> StoneRules {
+        stone_rules_from_config(DEFAULT_STONE_RULES).expect("The default rules are valid.")
+    }
+}
+
+impl StoneRules {
+    // Rules are not ordered, so every stone has to match exactly one of them.
+    fn apply(&self, stone_num: u64) -> Result<Vec<u64>, Box<dyn Error>> {
+        let matching: Vec<&StoneRule> = self
+            .rules
+            .iter()
+            .filter(|rule| {
+                rule.conditions
+                    .iter()
+                    .all(|condition| condition.matches(stone_num))
+            })
+            .collect();
+
+        match matching[..] {
+            [rule] => Ok(rule.action.apply(stone_num).ok_or(format!(
+                "Rule on line {} cannot be applied to stone {stone_num}",
+                rule.line_number
+            ))?),
+            [] => Err(format!("No rule matches stone {stone_num}").into()),
+            _ => Err(format!(
+                "Stone {stone_num} matches the rules on lines {}",
+                matching
+                    .iter()
+                    .map(|rule| rule.line_number.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+            .into()),
+        }
+    }
+}
+
+// One rule per line: "<condition> [and <condition>...] -> <action>", e.g.
+// "digits_multiple_of 2 -> split 2". Lines starting with # are comments.
+fn stone_rules_from_config(config: &str) -> Result<StoneRules, Box<dyn Error>> {
+    let mut rules = Vec::new();
+    for (index, line) in config.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parse_rule = || -> Result<StoneRule, Box<dyn Error>> {
+            let (conditions, action) = line
+                .split_once("->")
+                .ok_or("Expected \"<conditions> -> <action>\"")?;
+            let conditions = conditions
+                .split(" and ")
+                .map(|condition| {
+                    let (name, value) = condition
+                        .trim()
+                        .split_once(' ')
+                        .ok_or(format!("Condition \"{condition}\" has no value"))?;
+                    let value = value.trim();
+                    Ok(match name {
+                        "equals" => StoneCondition::Equals(value.parse()?),
+                        "not_equals" => StoneCondition::NotEquals(value.parse()?),
+                        "less_than" => StoneCondition::LessThan(value.parse()?),
+                        "at_least" => StoneCondition::AtLeast(value.parse()?),
+                        "digits_multiple_of" => StoneCondition::DigitsMultipleOf(value.parse()?),
+                        "digits_not_multiple_of" => {
+                            StoneCondition::DigitsNotMultipleOf(value.parse()?)
+                        }
+                        _ => return Err(format!("Unknown condition \"{name}\"").into()),
+                    })
+                })
+                .collect::<Result<Vec<StoneCondition>, Box<dyn Error>>>()?;
+
+            let action = match action.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["replace", value] => StoneAction::Replace(value.parse()?),
+                ["multiply", factor] => StoneAction::Multiply(factor.parse()?),
+                ["add", term] => StoneAction::Add(term.parse()?),
+                ["split", parts] => match parts.parse()? {
+                    0 => return Err("Cannot split into 0 parts".into()),
+                    parts => StoneAction::Split(parts),
+                },
+                ["digit_sum"] => StoneAction::DigitSum,
+                _ => return Err(format!("Unknown action \"{}\"", action.trim()).into()),
             };
+
+            Ok(StoneRule {
+                conditions,
+                action,
+                line_number,
+            })
         };
-        number_of_stones
+        rules.push(parse_rule().map_err(|error| format!("line {line_number}: {error}"))?);
+    }
+
+    Ok(StoneRules { rules })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BlinkStatistics {
+    blink: usize,
+    number_of_stones: StoneCount,
+    distinct_stones: usize,
+    distinct_stones_seen: usize,
+    largest_stone: u64,
+    // None for the first blink and after a blink that left no stones.
+    growth_ratio: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct StatisticsRecorder {
+    seen_stones: HashSet<u64>,
+    blinks: Vec<BlinkStatistics>,
+    // The first blink that produced no stone number that had not been seen before. All
+    // numbers the seen ones turn into have been seen by then, so the set stays the same
+    // from that blink on.
+    saturated_at_blink: Option<usize>,
+}
+
+impl StatisticsRecorder {
+    fn record(&mut self, blink: usize, stones: &HashMap<u64, StoneCount>) {
+        let mut number_of_stones = StoneCount::default();
+        for count in stones.values() {
+            number_of_stones += count;
+        }
+        let seen_before = self.seen_stones.len();
+        self.seen_stones.extend(stones.keys());
+        if blink > 0 && seen_before == self.seen_stones.len() && self.saturated_at_blink.is_none() {
+            self.saturated_at_blink = Some(blink);
+        }
+
+        let growth_ratio = self
+            .blinks
+            .last()
+            .map(|previous| previous.number_of_stones.to_f64())
+            .filter(|&previous_count| previous_count > 0.0)
+            .map(|previous_count| number_of_stones.to_f64() / previous_count);
+        self.blinks.push(BlinkStatistics {
+            blink,
+            number_of_stones,
+            distinct_stones: stones.len(),
+            distinct_stones_seen: self.seen_stones.len(),
+            largest_stone: stones.keys().copied().max().unwrap_or(0),
+            growth_ratio,
+        });
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "blink,number_of_stones,distinct_stones,distinct_stones_seen,largest_stone,growth_ratio\n",
+        );
+        for statistics in &self.blinks {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                statistics.blink,
+                statistics.number_of_stones,
+                statistics.distinct_stones,
+                statistics.distinct_stones_seen,
+                statistics.largest_stone,
+                statistics
+                    .growth_ratio
+                    .map_or(String::new(), |ratio| format!("{ratio:.6}"))
+            ));
+        }
+        csv
+    }
+}
+
+// Stones never influence each other, so only how many stones carry each number matters.
+// Memory is bounded by the number of distinct stone numbers instead of the number of stones.
+struct StoneEngine {
+    stones: HashMap<u64, StoneCount>,
+    rules: StoneRules,
+    blinks: usize,
+    statistics: Option<StatisticsRecorder>,
+}
+
+impl StoneEngine {
+    fn new(stone_numbers: &[u64], rules: StoneRules) -> StoneEngine {
+        let mut stones: HashMap<u64, StoneCount> = HashMap::new();
+        for &stone_num in stone_numbers {
+            *stones.entry(stone_num).or_default() += &StoneCount::from_u64(1);
+        }
+        StoneEngine {
+            stones,
+            rules,
+            blinks: 0,
+            statistics: None,
+        }
+    }
+
+    // Records statistics for the current state and every following blink.
+    fn with_statistics(mut self) -> StoneEngine {
+        let mut recorder = StatisticsRecorder::default();
+        recorder.record(self.blinks, &self.stones);
+        self.statistics = Some(recorder);
+        self
+    }
+
+    fn blink(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut next_stones: HashMap<u64, StoneCount> = HashMap::with_capacity(self.stones.len());
+        for (&stone_num, count) in &self.stones {
+            for next_stone_num in self.rules.apply(stone_num)? {
+                *next_stones.entry(next_stone_num).or_default() += count;
+            }
+        }
+        self.stones = next_stones;
+        self.blinks += 1;
+        if let Some(recorder) = &mut self.statistics {
+            recorder.record(self.blinks, &self.stones);
+        }
+        Ok(())
+    }
+
+    fn blink_until(&mut self, blinks: usize) -> Result<(), Box<dyn Error>> {
+        while self.blinks < blinks {
+            self.blink()?;
+        }
+        Ok(())
+    }
+
+    fn number_of_stones(&self) -> StoneCount {
+        let mut total = StoneCount::default();
+        for count in self.stones.values() {
+            total += count;
+        }
+        total
     }
 }
 
-fn part1(stone_numbers: Vec<i64>) -> Result<i64, Box<dyn Error>> {
-    Ok(number_of_stones_after_blinks(stone_numbers, 25))
+fn number_of_stones_after_blinks(
+    stone_numbers: &[u64],
+    blinks: usize,
+) -> Result<u128, Box<dyn Error>> {
+    let mut engine = StoneEngine::new(stone_numbers, StoneRules::default());
+    engine.blink_until(blinks)?;
+    Ok(engine
+        .number_of_stones()
+        .to_u128()
+        .ok_or(format!("More than u128::MAX stones after {blinks} blinks"))?)
 }
 
-fn part2(stone_numbers: Vec<i64>) -> Result<i64, Box<dyn Error>> {
-    Ok(number_of_stones_after_blinks(stone_numbers, 75))
+fn part1(stone_numbers: Vec<u64>) -> Result<u128, Box<dyn Error>> {
+    number_of_stones_after_blinks(&stone_numbers, 25)
+}
+
+fn part2(stone_numbers: Vec<u64>) -> Result<u128, Box<dyn Error>> {
+    number_of_stones_after_blinks(&stone_numbers, 75)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -55,6 +428,49 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let stone_numbers = stone_numbers_from_string(data)?;
 
+    // "blinks N [rules path]" prints the exact number of stones after N blinks, however
+    // large it gets. "statistics N [rules path]" prints per-blink statistics as CSV. Both
+    // use the stone rules from the config file when one is given.
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let rules_from = |rules_path: Option<&str>| -> Result<StoneRules, Box<dyn Error>> {
+        match rules_path {
+            Some(rules_path) => stone_rules_from_config(&std::fs::read_to_string(rules_path)?),
+            None => Ok(StoneRules::default()),
+        }
+    };
+    match arguments
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => {}
+        ["blinks", blinks, rules_path @ ..] if rules_path.len() <= 1 => {
+            let mut engine =
+                StoneEngine::new(&stone_numbers, rules_from(rules_path.first().copied())?);
+            engine.blink_until(blinks.parse::<usize>()?)?;
+            println!("{}", engine.number_of_stones());
+            return Ok(());
+        }
+        ["statistics", blinks, rules_path @ ..] if rules_path.len() <= 1 => {
+            let mut engine =
+                StoneEngine::new(&stone_numbers, rules_from(rules_path.first().copied())?)
+                    .with_statistics();
+            engine.blink_until(blinks.parse::<usize>()?)?;
+            let recorder = engine.statistics.ok_or("Statistics were not recorded")?;
+            print!("{}", recorder.to_csv());
+            // Reported on stderr so that stdout stays valid CSV.
+            match recorder.saturated_at_blink {
+                Some(blink) => {
+                    eprintln!("The set of distinct stones stopped growing at blink {blink}")
+                }
+                None => eprintln!("The set of distinct stones was still growing at the last blink"),
+            }
+            return Ok(());
+        }
+        _ => return Err(format!("Unknown arguments {arguments:?}").into()),
+    }
+
     let result_part1 = part1(stone_numbers.clone())?;
     let result_part2 = part2(stone_numbers.clone())?;
 
@@ -84,4 +500,109 @@ mod tests {
         assert!(result == 55312);
         Ok(())
     }
+
+    #[test]
+    fn test_counts_beyond_u128() -> Result<(), Box<dyn Error>> {
+        let stone_numbers = stone_numbers_from_string(get_test_data())?;
+        let mut engine = StoneEngine::new(&stone_numbers, StoneRules::default());
+        engine.blink_until(6)?;
+        assert_eq!(engine.number_of_stones().to_u128(), Some(22));
+
+        engine.blink_until(500)?;
+        let count = engine.number_of_stones();
+        assert_eq!(count.to_u128(), None);
+        assert!(count.to_string().len() > 39);
+        assert!(number_of_stones_after_blinks(&stone_numbers, 500).is_err());
+
+        let mut sum = StoneCount::from_u64(LIMB_BASE - 1);
+        sum += &StoneCount::from_u64(u64::MAX);
+        assert_eq!(
+            sum.to_u128(),
+            Some((LIMB_BASE - 1) as u128 + u64::MAX as u128)
+        );
+        assert_eq!(sum.to_string(), (sum.to_u128().unwrap()).to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stone_rules() -> Result<(), Box<dyn Error>> {
+        let rules = StoneRules::default();
+        assert_eq!(rules.apply(0)?, vec![1]);
+        assert_eq!(rules.apply(1000)?, vec![10, 0]);
+        assert_eq!(rules.apply(17)?, vec![1, 7]);
+        assert_eq!(rules.apply(125)?, vec![253000]);
+
+        let rules = stone_rules_from_config(
+            "# Three-way splits and digit sums
+            digits_multiple_of 3 -> split 3
+            digits_not_multiple_of 3 and at_least 10 -> digit_sum
+            less_than 10 -> multiply 37",
+        )?;
+        assert_eq!(rules.apply(123456)?, vec![12, 34, 56]);
+        assert_eq!(rules.apply(100200)?, vec![10, 2, 0]);
+        assert_eq!(rules.apply(4711)?, vec![13]);
+        assert_eq!(rules.apply(7)?, vec![259]);
+        let mut engine = StoneEngine::new(&[7], rules);
+        engine.blink_until(3)?;
+        assert_eq!(engine.number_of_stones().to_u128(), Some(3));
+
+        let overlapping = stone_rules_from_config("less_than 10 -> add 1\nequals 0 -> replace 1")?;
+        assert!(overlapping.apply(5).is_ok());
+        assert!(overlapping.apply(0).is_err());
+        assert!(overlapping.apply(10).is_err());
+
+        let unsplittable = stone_rules_from_config("at_least 0 -> split 2")?;
+        assert!(unsplittable.apply(123).is_err());
+        assert!(stone_rules_from_config("equals 0 -> explode").is_err());
+        assert!(stone_rules_from_config("equals -> replace 1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blink_statistics() -> Result<(), Box<dyn Error>> {
+        let stone_numbers = stone_numbers_from_string(get_test_data())?;
+        let mut engine = StoneEngine::new(&stone_numbers, StoneRules::default()).with_statistics();
+        engine.blink_until(6)?;
+        let recorder = engine.statistics.ok_or("Statistics were not recorded")?;
+
+        let counts: Vec<Option<u128>> = recorder
+            .blinks
+            .iter()
+            .map(|statistics| statistics.number_of_stones.to_u128())
+            .collect();
+        assert_eq!(counts, [2, 3, 4, 5, 9, 13, 22].map(Some).to_vec());
+        // After two blinks the stones are 253, 0, 2024 and 14168.
+        assert_eq!(recorder.blinks[2].distinct_stones, 4);
+        assert_eq!(recorder.blinks[2].largest_stone, 14168);
+        assert_eq!(recorder.blinks[0].growth_ratio, None);
+        assert_eq!(recorder.blinks[6].growth_ratio, Some(22.0 / 13.0));
+
+        let csv = recorder.to_csv();
+        assert_eq!(csv.lines().count(), 8);
+        assert_eq!(csv.lines().nth(1), Some("0,2,2,2,125,"));
+
+        let vanishing = stone_rules_from_config("at_least 0 -> replace 0")?;
+        let mut engine = StoneEngine::new(&[], vanishing).with_statistics();
+        engine.blink_until(2)?;
+        let csv = engine
+            .statistics
+            .ok_or("Statistics were not recorded")?
+            .to_csv();
+        assert_eq!(csv.lines().nth(2), Some("1,0,0,0,0,"));
+
+        // A single 0 cycles through 1, 2024, 20, 24, 2, 0, 4, ... and only ever reaches
+        // finitely many numbers.
+        let mut engine = StoneEngine::new(&[0], StoneRules::default()).with_statistics();
+        engine.blink_until(40)?;
+        let recorder = engine.statistics.ok_or("Statistics were not recorded")?;
+        let saturated_at_blink = recorder.saturated_at_blink.ok_or("Never saturated")?;
+        let seen_at_saturation = recorder.blinks[saturated_at_blink].distinct_stones_seen;
+        assert!(recorder
+            .blinks
+            .iter()
+            .skip(saturated_at_blink - 1)
+            .all(|statistics| statistics.distinct_stones_seen == seen_at_saturation));
+        assert!(recorder.blinks[saturated_at_blink - 2].distinct_stones_seen < seen_at_saturation);
+        Ok(())
+    }
 }
@@ -1,6 +1,6 @@
-use std::collections::{HashMap, HashSet};
+use advent_of_code_2024::config::key_value_pairs;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
-use std::hash::Hash;
 use std::ops::Add;
 
 fn garden_regions_from_string(data: String) -> Vec<Vec<char>> {
@@ -14,14 +14,6 @@ fn import_data() -> Result<String, Box<dyn Error>> {
     Ok(std::fs::read_to_string("data/day12.txt")?)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum EdgeSide {
-    Top,
-    Bottom,
-    Left,
-    Right,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Position {
     x: i32,
@@ -44,13 +36,21 @@ impl Position {
     }
 }
 
-impl Add for Position {
-    type Output = Position;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EdgeSide {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
 
-    fn add(self, other: Position) -> Position {
-        Position {
-            x: self.x + other.x,
-            y: self.y + other.y,
+impl EdgeSide {
+    fn outward(&self) -> Position {
+        match self {
+            EdgeSide::Top => Position::new(0, 1),
+            EdgeSide::Bottom => Position::new(0, -1),
+            EdgeSide::Left => Position::new(-1, 0),
+            EdgeSide::Right => Position::new(1, 0),
         }
     }
 }
@@ -65,206 +65,784 @@ impl Edge {
     fn new(position: Position, side: EdgeSide) -> Edge {
         Edge { position, side }
     }
+
+    // Corner where the edge starts when walked with the region on the right side.
+    fn start_point(&self) -> (i32, i32) {
+        let Position { x, y } = self.position;
+        match self.side {
+            EdgeSide::Top => (x + 1, y + 1),
+            EdgeSide::Left => (x, y + 1),
+            EdgeSide::Bottom => (x, y),
+            EdgeSide::Right => (x + 1, y),
+        }
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, other: Position) -> Position {
+        Position {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> UnionFind {
+        UnionFind {
+            parents: (0..size).collect(),
+            sizes: vec![1; size],
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return;
+        }
+        let (larger, smaller) = if self.sizes[root_a] >= self.sizes[root_b] {
+            (root_a, root_b)
+        } else {
+            (root_b, root_a)
+        };
+        self.parents[smaller] = larger;
+        self.sizes[larger] += self.sizes[smaller];
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RegionSides {
+    outer: i32,
+    holes: Vec<i32>,
+}
+
+impl RegionSides {
+    fn total(&self) -> i32 {
+        self.outer + self.holes.iter().sum::<i32>()
+    }
+}
+
+// Rings are closed lists of corner points, the outer ring runs counterclockwise
+// and the holes clockwise with y pointing up, as GeoJSON expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RegionPolygon {
+    outer: Vec<(i32, i32)>,
+    holes: Vec<Vec<(i32, i32)>>,
+}
+
+fn ring_to_corner_points(ring: &[Edge]) -> Vec<(i32, i32)> {
+    let mut points: Vec<(i32, i32)> = (0..ring.len())
+        .filter(|&index| ring[index].side != ring[(index + ring.len() - 1) % ring.len()].side)
+        .map(|index| ring[index].start_point())
+        .collect();
+    if let Some(&first) = points.first() {
+        points.push(first);
+    }
+    points
+}
+
+fn plant_at(garden_map: &[Vec<char>], position: Position) -> Option<char> {
+    if position.x < 0 || position.y < 0 {
+        return None;
+    }
+    garden_map
+        .get(position.y as usize)?
+        .get(position.x as usize)
+        .copied()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct GardenRegion {
-    positions: Vec<Position>,
+    positions: HashSet<Position>,
+    area: i32,
+    perimeter: i32,
+    number_of_sides: i32,
 }
 
 impl GardenRegion {
+    fn add_plot(&mut self, garden_map: &[Vec<char>], position: Position) {
+        let plant = plant_at(garden_map, position);
+        let is_same_plant = |offset: Position| plant_at(garden_map, position + offset) == plant;
+
+        let number_of_neighbors_in_region = [(1, 0), (0, -1), (-1, 0), (0, 1)]
+            .iter()
+            .filter(|&&(dx, dy)| is_same_plant(Position::new(dx, dy)))
+            .count() as i32;
+
+        // A region has as many sides as it has corners.
+        let number_of_corners = [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+            .iter()
+            .filter(|&&(dx, dy)| {
+                let horizontal = is_same_plant(Position::new(dx, 0));
+                let vertical = is_same_plant(Position::new(0, dy));
+                let diagonal = is_same_plant(Position::new(dx, dy));
+                (!horizontal && !vertical) || (horizontal && vertical && !diagonal)
+            })
+            .count() as i32;
+
+        self.positions.insert(position);
+        self.area += 1;
+        self.perimeter += 4 - number_of_neighbors_in_region;
+        self.number_of_sides += number_of_corners;
+    }
+
     fn perimeter(&self) -> i32 {
-        let positions_as_set: HashSet<Position> = self.positions.clone().into_iter().collect();
-        let mut perimiter = 0;
-        for position in self.positions.iter() {
-            let number_of_neighbors_in_regions = position
-                .get_neighbors()
-                .iter()
-                .filter(|potential_neighbor| positions_as_set.contains(potential_neighbor))
-                .count() as i32;
-            perimiter += 4 - number_of_neighbors_in_regions;
-        }
-        perimiter
+        self.perimeter
     }
 
     fn area(&self) -> i32 {
-        self.positions.len() as i32
+        self.area
     }
 
+    fn number_of_sides(&self) -> i32 {
+        self.number_of_sides
+    }
+
+    // Cells of the region touching diagonally are treated as connected, so every enclosed
+    // region ends up as its own hole rather than being merged with a neighbouring one.
     fn move_one_edge_keeping_region_rightside(&self, edge: Edge) -> Edge {
-        match edge.side {
-            EdgeSide::Top => {
-                if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(-1, 0)))
-                {
-                    Edge::new(edge.position, EdgeSide::Left)
-                } else if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(-1, 1)))
-                {
-                    Edge::new(edge.position + Position::new(-1, 0), EdgeSide::Top)
-                } else {
-                    Edge::new(edge.position + Position::new(-1, 1), EdgeSide::Right)
-                }
-            }
-            EdgeSide::Left => {
-                if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(0, -1)))
-                {
-                    Edge::new(edge.position, EdgeSide::Bottom)
-                } else if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(-1, -1)))
-                {
-                    Edge::new(edge.position + Position::new(0, -1), EdgeSide::Left)
-                } else {
-                    Edge::new(edge.position + Position::new(-1, -1), EdgeSide::Top)
-                }
-            }
-            EdgeSide::Bottom => {
-                if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(1, 0)))
-                {
-                    Edge::new(edge.position, EdgeSide::Right)
-                } else if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(1, -1)))
+        let (diagonal, straight, turned_side, straight_side, diagonal_side) = match edge.side {
+            EdgeSide::Top => (
+                Position::new(-1, 1),
+                Position::new(-1, 0),
+                EdgeSide::Left,
+                EdgeSide::Top,
+                EdgeSide::Right,
+            ),
+            EdgeSide::Left => (
+                Position::new(-1, -1),
+                Position::new(0, -1),
+                EdgeSide::Bottom,
+                EdgeSide::Left,
+                EdgeSide::Top,
+            ),
+            EdgeSide::Bottom => (
+                Position::new(1, -1),
+                Position::new(1, 0),
+                EdgeSide::Right,
+                EdgeSide::Bottom,
+                EdgeSide::Left,
+            ),
+            EdgeSide::Right => (
+                Position::new(1, 1),
+                Position::new(0, 1),
+                EdgeSide::Top,
+                EdgeSide::Right,
+                EdgeSide::Bottom,
+            ),
+        };
+
+        if self.positions.contains(&(edge.position + diagonal)) {
+            Edge::new(edge.position + diagonal, diagonal_side)
+        } else if self.positions.contains(&(edge.position + straight)) {
+            Edge::new(edge.position + straight, straight_side)
+        } else {
+            Edge::new(edge.position, turned_side)
+        }
+    }
+
+    // The first ring is the outer boundary, the remaining rings each surround a hole.
+    fn boundary_rings(&self) -> Vec<Vec<Edge>> {
+        let mut sorted_positions: Vec<Position> = self.positions.iter().copied().collect();
+        sorted_positions.sort_by_key(|position| (position.x, position.y));
+
+        let mut visited_edges: HashSet<Edge> = HashSet::new();
+        let mut rings = Vec::new();
+        for position in sorted_positions {
+            for side in [
+                EdgeSide::Left,
+                EdgeSide::Top,
+                EdgeSide::Right,
+                EdgeSide::Bottom,
+            ] {
+                let mut current_edge = Edge::new(position, side);
+                if self.positions.contains(&(position + side.outward()))
+                    || visited_edges.contains(&current_edge)
                 {
-                    Edge::new(edge.position + Position::new(1, 0), EdgeSide::Bottom)
-                } else {
-                    Edge::new(edge.position + Position::new(1, -1), EdgeSide::Left)
+                    continue;
                 }
-            }
-            EdgeSide::Right => {
-                if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(0, 1)))
-                {
-                    Edge::new(edge.position, EdgeSide::Top)
-                } else if !self
-                    .positions
-                    .contains(&(edge.position + Position::new(1, 1)))
-                {
-                    Edge::new(edge.position + Position::new(0, 1), EdgeSide::Right)
-                } else {
-                    Edge::new(edge.position + Position::new(1, 1), EdgeSide::Bottom)
+
+                let mut ring = Vec::new();
+                while visited_edges.insert(current_edge) {
+                    ring.push(current_edge);
+                    current_edge = self.move_one_edge_keeping_region_rightside(current_edge);
                 }
+                rings.push(ring);
             }
         }
+        rings
     }
 
-    fn number_of_sides(&self) -> i32 {
-        let mut visited_edges: HashSet<Edge> = HashSet::new();
-        let mut number_of_sides = 0;
-        while let Some(position_with_left_edge) = self
-            .positions
+    fn sides(&self) -> RegionSides {
+        let mut sides_per_ring = self.boundary_rings().into_iter().map(|ring| {
+            (0..ring.len())
+                .filter(|&index| ring[index].side != ring[(index + 1) % ring.len()].side)
+                .count() as i32
+        });
+
+        RegionSides {
+            outer: sides_per_ring.next().unwrap_or(0),
+            holes: sides_per_ring.collect(),
+        }
+    }
+
+    fn first_position(&self) -> Position {
+        self.positions
             .iter()
-            .filter(|&&position| !self.positions.contains(&(position + Position::new(-1, 0))))
-            .find(|&&position| !visited_edges.contains(&Edge::new(position, EdgeSide::Left)))
-        {
-            let mut current_edge = Edge::new(*position_with_left_edge, EdgeSide::Left);
-            while !visited_edges.contains(&current_edge) {
-                visited_edges.insert(current_edge);
-                let next_edge = self.move_one_edge_keeping_region_rightside(current_edge);
-                if next_edge.side != current_edge.side {
-                    number_of_sides += 1;
-                }
-                current_edge = next_edge
-            }
+            .copied()
+            .min_by_key(|position| (position.y, position.x))
+            .unwrap_or(Position::new(0, 0))
+    }
+
+    fn polygon(&self) -> RegionPolygon {
+        let mut rings = self
+            .boundary_rings()
+            .into_iter()
+            .map(|ring| ring_to_corner_points(&ring));
+
+        RegionPolygon {
+            outer: rings.next().unwrap_or_default(),
+            holes: rings.collect(),
         }
-        number_of_sides
     }
+}
 
-    fn split_region_into_connected_regions(&self) -> Vec<GardenRegion> {
-        let mut remaining_positions: HashSet<Position> =
-            self.positions.clone().into_iter().collect();
+trait PricingModel {
+    fn price(&self, plant: char, region: &GardenRegion) -> i32;
+}
 
-        let mut split_groups: Vec<GardenRegion> = Vec::new();
-        while let Some(&start_point) = remaining_positions.iter().next() {
-            let mut positions_in_new_region = Vec::new();
-            let mut queue: Vec<Position> = Vec::new();
-            queue.push(start_point);
+struct PerimeterPricing;
 
-            while let Some(current) = queue.pop() {
-                if remaining_positions.remove(&current) {
-                    positions_in_new_region.push(current);
-                    for neighbor in current.get_neighbors() {
-                        if remaining_positions.contains(&neighbor) {
-                            queue.push(neighbor);
-                        }
-                    }
+impl PricingModel for PerimeterPricing {
+    fn price(&self, _plant: char, region: &GardenRegion) -> i32 {
+        region.perimeter() * region.area()
+    }
+}
+
+struct SidesPricing;
+
+impl PricingModel for SidesPricing {
+    fn price(&self, _plant: char, region: &GardenRegion) -> i32 {
+        region.number_of_sides() * region.area()
+    }
+}
+
+// Every corner of the fence needs a post, and a region has as many corners as sides.
+struct CornerPricing {
+    price_per_corner: i32,
+}
+
+impl PricingModel for CornerPricing {
+    fn price(&self, _plant: char, region: &GardenRegion) -> i32 {
+        region.number_of_sides() * self.price_per_corner
+    }
+}
+
+// Fences the region with its convex hull, rounded up to whole fence units.
+struct ConvexHullPricing;
+
+impl PricingModel for ConvexHullPricing {
+    fn price(&self, _plant: char, region: &GardenRegion) -> i32 {
+        let hull = convex_hull(
+            region
+                .positions
+                .iter()
+                .flat_map(|&position| {
+                    [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .map(|(dx, dy)| (position.x + dx, position.y + dy))
+                })
+                .collect(),
+        );
+        let hull_perimeter: f64 = (0..hull.len())
+            .map(|index| {
+                let (a, b) = (hull[index], hull[(index + 1) % hull.len()]);
+                (((a.0 - b.0).pow(2) + (a.1 - b.1).pow(2)) as f64).sqrt()
+            })
+            .sum();
+        hull_perimeter.ceil() as i32 * region.area()
+    }
+}
+
+struct PlantDiscountPricing {
+    base: Box<dyn PricingModel>,
+    discount_percentages: HashMap<char, i32>,
+}
+
+impl PricingModel for PlantDiscountPricing {
+    fn price(&self, plant: char, region: &GardenRegion) -> i32 {
+        let discount = self.discount_percentages.get(&plant).copied().unwrap_or(0);
+        self.base.price(plant, region) * (100 - discount) / 100
+    }
+}
+
+fn convex_hull(mut points: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
+    points.sort();
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: (i32, i32), a: (i32, i32), b: (i32, i32)| {
+        (a.0 - o.0) as i64 * (b.1 - o.1) as i64 - (a.1 - o.1) as i64 * (b.0 - o.0) as i64
+    };
+    let mut hull: Vec<(i32, i32)> = Vec::new();
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+// The config has one "key = value" per line: "model" picks perimeter, sides, corners or
+// convex_hull, "price_per_corner" sets the corner price and "discount.<plant>" gives a
+// discount in percent for that plant type.
+fn pricing_model_from_config(config: &str) -> Result<Box<dyn PricingModel>, Box<dyn Error>> {
+    let mut model_name = "perimeter".to_string();
+    let mut price_per_corner = 1;
+    let mut discount_percentages = HashMap::new();
+
+    for (key, value) in key_value_pairs(config)? {
+        match key {
+            "model" => model_name = value.to_string(),
+            "price_per_corner" => {
+                price_per_corner = value.parse::<i32>()?;
+                if price_per_corner < 0 {
+                    return Err(format!("{key} must not be negative, got {value}").into());
                 }
             }
-            split_groups.push(GardenRegion {
-                positions: positions_in_new_region,
-            });
+            _ => match key
+                .strip_prefix("discount.")
+                .map(|plant| plant.chars().collect::<Vec<char>>())
+            {
+                Some(plant) if plant.len() == 1 => {
+                    let discount = value.parse::<i32>()?;
+                    if !(0..=100).contains(&discount) {
+                        return Err(format!(
+                            "{key} must be a percentage from 0 to 100, got {value}"
+                        )
+                        .into());
+                    }
+                    discount_percentages.insert(plant[0], discount);
+                }
+                _ => return Err(format!("Unknown pricing config key \"{key}\"").into()),
+            },
         }
-        split_groups
+    }
+
+    let model: Box<dyn PricingModel> = match model_name.as_str() {
+        "perimeter" => Box::new(PerimeterPricing),
+        "sides" => Box::new(SidesPricing),
+        "corners" => Box::new(CornerPricing { price_per_corner }),
+        "convex_hull" => Box::new(ConvexHullPricing),
+        _ => return Err(format!("Unknown pricing model \"{model_name}\"").into()),
+    };
+
+    if discount_percentages.is_empty() {
+        Ok(model)
+    } else {
+        Ok(Box::new(PlantDiscountPricing {
+            base: model,
+            discount_percentages,
+        }))
     }
 }
 
 struct Garden {
     regions: Vec<(char, GardenRegion)>,
+    labels: Vec<Vec<usize>>,
 }
 
 impl Garden {
-    fn from_garden_map(garden_map: &Vec<Vec<char>>) -> Garden {
-        let mut regions: HashMap<char, GardenRegion> = HashMap::new();
-        for (y, row) in garden_map.iter().enumerate() {
-            for (x, character) in row.iter().enumerate() {
-                let position = Position {
-                    x: x as i32,
-                    y: y as i32,
-                };
-                regions
-                    .entry(*character)
-                    .or_insert(GardenRegion {
-                        positions: Vec::new(),
-                    })
-                    .positions
-                    .push(position);
+    fn from_garden_map(garden_map: &[Vec<char>]) -> Garden {
+        let width = garden_map.iter().map(|row| row.len()).max().unwrap_or(0);
+        let index_of = |position: Position| position.y as usize * width + position.x as usize;
+        let all_positions = || {
+            garden_map
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| (0..row.len()).map(move |x| Position::new(x as i32, y as i32)))
+        };
+
+        let mut union_find = UnionFind::new(width * garden_map.len());
+        for position in all_positions() {
+            let plant = plant_at(garden_map, position);
+            for neighbor in [
+                position + Position::new(1, 0),
+                position + Position::new(0, 1),
+            ] {
+                if plant_at(garden_map, neighbor) == plant {
+                    union_find.union(index_of(position), index_of(neighbor));
+                }
             }
         }
 
-        let all_distinct_regions = regions
+        let mut region_index_of_root: HashMap<usize, usize> = HashMap::new();
+        let mut regions: Vec<(char, GardenRegion)> = Vec::new();
+        let mut labels: Vec<Vec<usize>> = garden_map.iter().map(|row| vec![0; row.len()]).collect();
+        for position in all_positions() {
+            let root = union_find.find(index_of(position));
+            let region_index = *region_index_of_root.entry(root).or_insert_with(|| {
+                let plant = plant_at(garden_map, position).unwrap_or_default();
+                regions.push((plant, GardenRegion::default()));
+                regions.len() - 1
+            });
+            labels[position.y as usize][position.x as usize] = region_index;
+            regions[region_index].1.add_plot(garden_map, position);
+        }
+
+        Garden { regions, labels }
+    }
+
+    fn region_at(&self, position: Position) -> Option<usize> {
+        if position.x < 0 || position.y < 0 {
+            return None;
+        }
+        self.labels
+            .get(position.y as usize)?
+            .get(position.x as usize)
+            .copied()
+    }
+
+    fn neighbors_of(&self, region_index: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.regions[region_index]
+            .1
+            .positions
             .iter()
-            .flat_map(|(&character, region)| {
-                region
-                    .split_region_into_connected_regions()
-                    .into_iter()
-                    .map(|split_region| (character, split_region))
-                    .collect::<Vec<(char, GardenRegion)>>()
+            .flat_map(|position| position.get_neighbors())
+            .filter_map(|neighbor| self.region_at(neighbor))
+            .filter(|&neighbor_index| neighbor_index != region_index)
+            .collect();
+        neighbors.sort();
+        neighbors.dedup();
+        neighbors
+    }
+
+    // A region is enclosed when it cannot reach the edge of the map without crossing the
+    // enclosing region, so everything left after a flood fill from the map edge qualifies.
+    fn regions_enclosed_by(&self, region_index: usize) -> Vec<usize> {
+        let mut reached: HashSet<Position> = HashSet::new();
+        let mut queue: Vec<Position> = self
+            .labels
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| Position::new(x as i32, y as i32)))
+            .filter(|&position| {
+                position
+                    .get_neighbors()
+                    .iter()
+                    .any(|&neighbor| self.region_at(neighbor).is_none())
             })
             .collect();
-        Garden {
-            regions: all_distinct_regions,
+
+        while let Some(position) = queue.pop() {
+            if self.region_at(position) == Some(region_index) || !reached.insert(position) {
+                continue;
+            }
+            queue.extend(
+                position
+                    .get_neighbors()
+                    .into_iter()
+                    .filter(|&neighbor| self.region_at(neighbor).is_some()),
+            );
         }
+
+        (0..self.regions.len())
+            .filter(|&index| index != region_index)
+            .filter(|&index| !reached.contains(&self.regions[index].1.first_position()))
+            .collect()
     }
 
-    fn total_price(&self) -> i32 {
+    fn largest_region_per_plant(&self) -> BTreeMap<char, usize> {
+        let mut largest: BTreeMap<char, usize> = BTreeMap::new();
+        for (index, (plant, region)) in self.regions.iter().enumerate() {
+            let current = largest.entry(*plant).or_insert(index);
+            if region.area() > self.regions[*current].1.area() {
+                *current = index;
+            }
+        }
+        largest
+    }
+
+    fn price_breakdown_table(&self) -> String {
+        let mut table = format!(
+            "{:>6} {:>5} {:>12} {:>6} {:>9} {:>6} {:>8} {:>10}\n",
+            "Region", "Plant", "Position", "Area", "Perimeter", "Sides", "Price", "Bulk price"
+        );
+        for (index, (plant, region)) in self.regions.iter().enumerate() {
+            let position = region.first_position();
+            table.push_str(&format!(
+                "{:>6} {:>5} {:>12} {:>6} {:>9} {:>6} {:>8} {:>10}\n",
+                index,
+                plant,
+                format!("({}, {})", position.x, position.y),
+                region.area(),
+                region.perimeter(),
+                region.number_of_sides(),
+                region.area() * region.perimeter(),
+                region.area() * region.number_of_sides()
+            ));
+        }
+        table
+    }
+
+    fn total_price_with(&self, pricing_model: &dyn PricingModel) -> i32 {
         self.regions
             .iter()
-            .map(|(_, region)| region.perimeter() * region.area())
+            .map(|(plant, region)| pricing_model.price(*plant, region))
             .sum()
     }
 
+    fn total_price(&self) -> i32 {
+        self.total_price_with(&PerimeterPricing)
+    }
+
     fn total_bulk_discounted_price(&self) -> i32 {
+        self.total_price_with(&SidesPricing)
+    }
+
+    fn size(&self) -> (i32, i32) {
         self.regions
             .iter()
-            .map(|(_, region)| region.number_of_sides() * region.area())
-            .sum()
+            .flat_map(|(_, region)| region.positions.iter())
+            .fold((0, 0), |size, position| {
+                (size.0.max(position.x + 1), size.1.max(position.y + 1))
+            })
+    }
+
+    fn to_geojson(&self) -> String {
+        let format_ring = |ring: &Vec<(i32, i32)>| {
+            let points: Vec<String> = ring.iter().map(|(x, y)| format!("[{x},{y}]")).collect();
+            format!("[{}]", points.join(","))
+        };
+
+        let features: Vec<String> = self
+            .regions
+            .iter()
+            .map(|(plant, region)| {
+                let polygon = region.polygon();
+                let rings: Vec<String> = std::iter::once(&polygon.outer)
+                    .chain(polygon.holes.iter())
+                    .map(format_ring)
+                    .collect();
+                format!(
+                    "{{\"type\":\"Feature\",\"properties\":{{\"plant\":\"{}\",\"area\":{},\"perimeter\":{},\"sides\":{}}},\"geometry\":{{\"type\":\"Polygon\",\"coordinates\":[{}]}}}}",
+                    escape_json(*plant),
+                    region.area(),
+                    region.perimeter(),
+                    region.number_of_sides(),
+                    rings.join(",")
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"type\":\"FeatureCollection\",\"features\":[\n{}\n]}}",
+            features.join(",\n")
+        )
+    }
+
+    fn to_svg(&self) -> String {
+        let (width, height) = self.size();
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {height}\">\n"
+        );
+
+        for (plant, region) in self.regions.iter() {
+            let polygon = region.polygon();
+            // SVG has y pointing down, so the rows are flipped back to the order of the map.
+            let path: Vec<String> = std::iter::once(&polygon.outer)
+                .chain(polygon.holes.iter())
+                .map(|ring| {
+                    let points: Vec<String> = ring[..ring.len() - 1]
+                        .iter()
+                        .map(|(x, y)| format!("{x} {}", height - y))
+                        .collect();
+                    format!("M {} Z", points.join(" L "))
+                })
+                .collect();
+            let hue = (*plant as u32 * 137) % 360;
+
+            svg.push_str(&format!(
+                "  <path d=\"{}\" fill=\"hsl({hue}, 70%, 60%)\" fill-rule=\"evenodd\" stroke=\"black\" stroke-width=\"0.05\"><title>{}</title></path>\n",
+                path.join(" "),
+                escape_xml(*plant)
+            ));
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_json(plant: char) -> String {
+    match plant {
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        _ if plant.is_control() => format!("\\u{:04x}", plant as u32),
+        _ => plant.to_string(),
+    }
+}
+
+fn escape_xml(plant: char) -> String {
+    match plant {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        '\'' => "&apos;".to_string(),
+        _ => plant.to_string(),
+    }
+}
+
+struct EditableGarden {
+    garden_map: Vec<Vec<char>>,
+    garden: Garden,
+}
+
+impl EditableGarden {
+    fn new(garden_map: Vec<Vec<char>>) -> EditableGarden {
+        let garden = Garden::from_garden_map(&garden_map);
+        EditableGarden { garden_map, garden }
+    }
+
+    // Only regions of the old and new plant type around the plot can change, so those are
+    // relabelled and remeasured while every other region is left untouched.
+    fn set_plot(&mut self, position: Position, plant: char) -> Result<(), Box<dyn Error>> {
+        let old_plant = plant_at(&self.garden_map, position).ok_or(format!(
+            "Position ({}, {}) is outside the garden",
+            position.x, position.y
+        ))?;
+        if old_plant == plant {
+            return Ok(());
+        }
+
+        let mut affected_regions: Vec<usize> = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| position + Position::new(dx, dy)))
+            .filter_map(|neighbor| self.garden.region_at(neighbor))
+            .filter(|&index| [old_plant, plant].contains(&self.garden.regions[index].0))
+            .collect();
+        affected_regions.sort();
+        affected_regions.dedup();
+
+        self.garden_map[position.y as usize][position.x as usize] = plant;
+
+        let mut remaining_positions: HashSet<Position> = affected_regions
+            .iter()
+            .flat_map(|&index| self.garden.regions[index].1.positions.iter().copied())
+            .collect();
+        let mut new_regions: Vec<(char, GardenRegion)> = Vec::new();
+        let mut sorted_positions: Vec<Position> = remaining_positions.iter().copied().collect();
+        sorted_positions.sort_by_key(|position| (position.y, position.x));
+        for start in sorted_positions {
+            if !remaining_positions.remove(&start) {
+                continue;
+            }
+            let region_plant = self.garden_map[start.y as usize][start.x as usize];
+            let mut region = GardenRegion::default();
+            let mut queue = vec![start];
+            while let Some(current) = queue.pop() {
+                region.add_plot(&self.garden_map, current);
+                for neighbor in current.get_neighbors() {
+                    if plant_at(&self.garden_map, neighbor) == Some(region_plant)
+                        && remaining_positions.remove(&neighbor)
+                    {
+                        queue.push(neighbor);
+                    }
+                }
+            }
+            new_regions.push((region_plant, region));
+        }
+
+        let number_of_reused_slots = new_regions.len().min(affected_regions.len());
+        for (slot, new_region) in new_regions.into_iter().enumerate() {
+            let region_index = if slot < number_of_reused_slots {
+                self.garden.regions[affected_regions[slot]] = new_region;
+                affected_regions[slot]
+            } else {
+                self.garden.regions.push(new_region);
+                self.garden.regions.len() - 1
+            };
+            self.relabel(region_index);
+        }
+
+        for &region_index in affected_regions[number_of_reused_slots..].iter().rev() {
+            self.garden.regions.swap_remove(region_index);
+            if region_index < self.garden.regions.len() {
+                self.relabel(region_index);
+            }
+        }
+        Ok(())
+    }
+
+    fn relabel(&mut self, region_index: usize) {
+        for position in self.garden.regions[region_index].1.positions.iter() {
+            self.garden.labels[position.y as usize][position.x as usize] = region_index;
+        }
+    }
+}
+
+fn print_region_sides(garden: &Garden) {
+    for (plant, region) in garden.regions.iter() {
+        let sides = region.sides();
+        println!(
+            "Region {plant} with area {}: {} outer sides, holes with {:?} sides, {} in total",
+            region.area(),
+            sides.outer,
+            sides.holes,
+            sides.total()
+        );
     }
 }
 
-fn part1(garden_regions: &Vec<Vec<char>>) -> Result<i32, Box<dyn Error>> {
+fn print_region_details(garden: &Garden, position: Position) {
+    match garden.region_at(position) {
+        Some(region_index) => {
+            let (plant, region) = &garden.regions[region_index];
+            println!(
+                "Position ({}, {}) is in region {region_index} of plant {plant} with area {}",
+                position.x,
+                position.y,
+                region.area()
+            );
+            println!("Neighbors: {:?}", garden.neighbors_of(region_index));
+            println!("Encloses: {:?}", garden.regions_enclosed_by(region_index));
+        }
+        None => println!(
+            "Position ({}, {}) is outside the garden",
+            position.x, position.y
+        ),
+    }
+}
+
+fn part1(garden_regions: &[Vec<char>]) -> Result<i32, Box<dyn Error>> {
     let garden = Garden::from_garden_map(garden_regions);
     Ok(garden.total_price())
 }
 
-fn part2(garden_regions: &Vec<Vec<char>>) -> Result<i32, Box<dyn Error>> {
+fn part2(garden_regions: &[Vec<char>]) -> Result<i32, Box<dyn Error>> {
     let garden = Garden::from_garden_map(garden_regions);
     Ok(garden.total_bulk_discounted_price())
 }
@@ -274,11 +852,68 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let garden_regions = garden_regions_from_string(data);
 
-    let result_part1 = part1(&garden_regions)?;
-    let result_part2 = part2(&garden_regions)?;
+    // Positions are given with y counted from the bottom row of the map.
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    match arguments
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        ["sides"] => print_region_sides(&Garden::from_garden_map(&garden_regions)),
+        ["geojson"] => println!("{}", Garden::from_garden_map(&garden_regions).to_geojson()),
+        ["svg"] => print!("{}", Garden::from_garden_map(&garden_regions).to_svg()),
+        ["regions"] => print!(
+            "{}",
+            Garden::from_garden_map(&garden_regions).price_breakdown_table()
+        ),
+        ["region", x, y] => print_region_details(
+            &Garden::from_garden_map(&garden_regions),
+            Position::new(x.parse::<i32>()?, y.parse::<i32>()?),
+        ),
+        ["price", config_path] => {
+            let pricing_model = pricing_model_from_config(&std::fs::read_to_string(config_path)?)?;
+            let total = Garden::from_garden_map(&garden_regions).total_price_with(&*pricing_model);
+            println!("Total price is: {total}");
+        }
+        ["edit", edits @ ..] => {
+            if edits.is_empty() || edits.len() % 3 != 0 {
+                return Err(
+                    format!("Expected edits as \"x y plant\" triples, got {edits:?}").into(),
+                );
+            }
+            let mut editable_garden = EditableGarden::new(garden_regions);
+            for edit in edits.chunks(3) {
+                let position = Position::new(edit[0].parse::<i32>()?, edit[1].parse::<i32>()?);
+                let plant = edit[2].chars().next().ok_or("Expected a plant type")?;
+                editable_garden.set_plot(position, plant)?;
+                println!(
+                    "After planting {plant} at ({}, {}): price {}, bulk discounted price {}",
+                    position.x,
+                    position.y,
+                    editable_garden.garden.total_price(),
+                    editable_garden.garden.total_bulk_discounted_price()
+                );
+            }
+        }
+        ["largest"] => {
+            let garden = Garden::from_garden_map(&garden_regions);
+            for (plant, region_index) in garden.largest_region_per_plant() {
+                let area = garden.regions[region_index].1.area();
+                println!(
+                    "Largest region of plant {plant} is region {region_index} with area {area}"
+                );
+            }
+        }
+        [] => {
+            let result_part1 = part1(&garden_regions)?;
+            let result_part2 = part2(&garden_regions)?;
 
-    println!("Result of part1 is: {result_part1}");
-    println!("Result of part2 is: {result_part2}");
+            println!("Result of part1 is: {result_part1}");
+            println!("Result of part2 is: {result_part2}");
+        }
+        _ => return Err(format!("Unknown arguments {arguments:?}").into()),
+    }
 
     Ok(())
 }
@@ -286,6 +921,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2024::random::SeededGenerator;
 
     fn get_test_data() -> String {
         "RRRRIICCFF
@@ -327,4 +963,350 @@ mod tests {
         assert!(result == 1206);
         Ok(())
     }
+
+    #[test]
+    fn test_separate_regions_of_the_same_plant() -> Result<(), Box<dyn Error>> {
+        let data_as_string = "OOOOO
+        OXOXO
+        OOOOO
+        OXOXO
+        OOOOO"
+            .replace("    ", "")
+            .to_string();
+
+        let garden_regions = garden_regions_from_string(data_as_string);
+        let garden = Garden::from_garden_map(&garden_regions);
+
+        assert_eq!(garden.regions.len(), 5);
+        assert_eq!(garden.total_price(), 772);
+        assert_eq!(garden.total_bulk_discounted_price(), 436);
+        Ok(())
+    }
+
+    fn sides_of_region_at(garden: &Garden, position: Position) -> RegionSides {
+        garden
+            .regions
+            .iter()
+            .find(|(_, region)| region.positions.contains(&position))
+            .map(|(_, region)| region.sides())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sides_of_region_with_touching_holes() {
+        let data_as_string = "AAAAAA
+        AAABBA
+        AAABBA
+        ABBAAA
+        ABBAAA
+        AAAAAA"
+            .replace("    ", "")
+            .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+
+        assert_eq!(
+            sides_of_region_at(&garden, Position::new(0, 0)),
+            RegionSides {
+                outer: 4,
+                holes: vec![4, 4]
+            }
+        );
+        assert_eq!(garden.total_bulk_discounted_price(), 368);
+    }
+
+    #[test]
+    fn test_sides_of_region_enclosing_other_regions() {
+        let data_as_string = "AAAAAAA
+        ABACCCA
+        AAACDCA
+        AAACCCA
+        AAAAAAA"
+            .replace("    ", "")
+            .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+
+        assert_eq!(
+            sides_of_region_at(&garden, Position::new(0, 0)),
+            RegionSides {
+                outer: 4,
+                holes: vec![4, 4]
+            }
+        );
+        assert_eq!(
+            sides_of_region_at(&garden, Position::new(3, 1)),
+            RegionSides {
+                outer: 4,
+                holes: vec![4]
+            }
+        );
+    }
+
+    #[test]
+    fn test_sides_of_region_touching_itself_diagonally() {
+        let data_as_string = "AAA
+        ABA
+        AAB"
+        .replace("    ", "")
+        .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+
+        assert_eq!(
+            sides_of_region_at(&garden, Position::new(0, 0)),
+            RegionSides {
+                outer: 6,
+                holes: vec![4]
+            }
+        );
+    }
+
+    #[test]
+    fn test_sides_match_corner_count() {
+        let data_as_string = "EEEEE
+        EXXXX
+        EEEEE
+        EXXXX
+        EEEEE"
+            .replace("    ", "")
+            .to_string();
+
+        for data in [data_as_string, get_test_data()] {
+            let garden = Garden::from_garden_map(&garden_regions_from_string(data));
+            for (_, region) in garden.regions.iter() {
+                assert_eq!(region.sides().total(), region.number_of_sides());
+            }
+        }
+    }
+
+    #[test]
+    fn test_polygon_of_region_with_hole() {
+        let data_as_string = "AAA
+        ABA
+        AAA"
+        .replace("    ", "")
+        .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+        let (_, region) = garden
+            .regions
+            .iter()
+            .find(|(plant, _)| *plant == 'A')
+            .unwrap();
+
+        assert_eq!(
+            region.polygon(),
+            RegionPolygon {
+                outer: vec![(0, 0), (3, 0), (3, 3), (0, 3), (0, 0)],
+                holes: vec![vec![(1, 1), (1, 2), (2, 2), (2, 1), (1, 1)]],
+            }
+        );
+    }
+
+    #[test]
+    fn test_exports() {
+        let data_as_string = "AAB
+        ACB"
+        .replace("    ", "")
+        .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+
+        let geojson = garden.to_geojson();
+        assert!(geojson.starts_with("{\"type\":\"FeatureCollection\""));
+        assert_eq!(geojson.matches("\"type\":\"Feature\"").count(), 3);
+        assert!(geojson.contains("\"plant\":\"A\",\"area\":3,\"perimeter\":8,\"sides\":6"));
+        assert!(geojson.contains("[[[0,0],[1,0],[1,1],[2,1],[2,2],[0,2],[0,0]]]"));
+
+        let svg = garden.to_svg();
+        assert!(svg.contains("viewBox=\"0 0 3 2\""));
+        assert_eq!(svg.matches("<path").count(), 3);
+        assert!(svg.contains("d=\"M 0 2 L 1 2 L 1 1 L 2 1 L 2 0 L 0 0 Z\""));
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string("\"\\\n<&".to_string()));
+        let geojson = garden.to_geojson();
+        assert!(geojson.contains("\"plant\":\"\\\"\""));
+        assert!(geojson.contains("\"plant\":\"\\\\\""));
+        let svg = garden.to_svg();
+        assert!(svg.contains("<title>&lt;</title>"));
+        assert!(svg.contains("<title>&amp;</title>"));
+    }
+
+    #[test]
+    fn test_region_queries() {
+        let data_as_string = "AAAAAAA
+        ABACCCA
+        AAACDCA
+        AAACCCA
+        AAAAAAB"
+            .replace("    ", "")
+            .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+        let region_a = garden.region_at(Position::new(0, 0)).unwrap();
+        let region_b_inside = garden.region_at(Position::new(1, 3)).unwrap();
+        let region_b_corner = garden.region_at(Position::new(6, 0)).unwrap();
+        let region_c = garden.region_at(Position::new(3, 1)).unwrap();
+        let region_d = garden.region_at(Position::new(4, 2)).unwrap();
+
+        assert_eq!(garden.region_at(Position::new(7, 0)), None);
+        assert_eq!(garden.regions[region_a].1.area(), 24);
+
+        let mut expected_neighbors = vec![region_b_inside, region_b_corner, region_c];
+        expected_neighbors.sort();
+        assert_eq!(garden.neighbors_of(region_a), expected_neighbors);
+        assert_eq!(garden.neighbors_of(region_d), vec![region_c]);
+
+        let mut expected_enclosed = vec![region_b_inside, region_c, region_d];
+        expected_enclosed.sort();
+        assert_eq!(garden.regions_enclosed_by(region_a), expected_enclosed);
+        assert_eq!(garden.regions_enclosed_by(region_c), vec![region_d]);
+        assert_eq!(
+            garden.regions_enclosed_by(region_b_corner),
+            Vec::<usize>::new()
+        );
+
+        let largest = garden.largest_region_per_plant();
+        assert_eq!(largest[&'A'], region_a);
+        assert_eq!(largest.len(), 4);
+        assert_eq!(garden.regions[largest[&'B']].1.area(), 1);
+    }
+
+    #[test]
+    fn test_price_breakdown_table() {
+        let data_as_string = "AAB
+        ACB"
+        .replace("    ", "")
+        .to_string();
+
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+        let table = garden.price_breakdown_table();
+
+        assert_eq!(table.lines().count(), 4);
+        assert!(
+            table.contains("     0     A       (0, 0)      3         8      6       24         18")
+        );
+    }
+
+    #[test]
+    fn test_pricing_models() -> Result<(), Box<dyn Error>> {
+        let data_as_string = "AAB
+        ACB"
+        .replace("    ", "")
+        .to_string();
+        let garden = Garden::from_garden_map(&garden_regions_from_string(data_as_string));
+
+        assert_eq!(
+            garden.total_price_with(&CornerPricing {
+                price_per_corner: 2
+            }),
+            28
+        );
+        // A has a convex hull perimeter of 6 + sqrt(2), rounded up to 8.
+        assert_eq!(
+            garden.total_price_with(&ConvexHullPricing),
+            3 * 8 + 2 * 6 + 4
+        );
+
+        let config = "# Discounted bulk pricing
+        model = sides
+        discount.A = 50";
+        let pricing_model = pricing_model_from_config(config)?;
+        assert_eq!(garden.total_price_with(&*pricing_model), 9 + 8 + 4);
+
+        assert_eq!(
+            garden.total_price_with(&*pricing_model_from_config("")?),
+            garden.total_price()
+        );
+        assert!(pricing_model_from_config("model = gold").is_err());
+        assert!(pricing_model_from_config("discount.AB = 10").is_err());
+        for (config, key) in [
+            ("discount.A = 150", "discount.A"),
+            ("discount.A = -20", "discount.A"),
+            ("price_per_corner = -1", "price_per_corner"),
+        ] {
+            let error = pricing_model_from_config(config)
+                .err()
+                .ok_or("Expected an error")?;
+            assert!(error.to_string().contains(key));
+        }
+        Ok(())
+    }
+
+    type RegionSummary = (char, i32, i32, i32, Vec<(i32, i32)>);
+
+    fn region_summaries(garden: &Garden) -> Vec<RegionSummary> {
+        let mut summaries: Vec<RegionSummary> = garden
+            .regions
+            .iter()
+            .map(|(plant, region)| {
+                let mut positions: Vec<(i32, i32)> =
+                    region.positions.iter().map(|p| (p.x, p.y)).collect();
+                positions.sort();
+                (
+                    *plant,
+                    region.area(),
+                    region.perimeter(),
+                    region.number_of_sides(),
+                    positions,
+                )
+            })
+            .collect();
+        summaries.sort();
+        summaries
+    }
+
+    fn assert_labels_match_regions(garden: &Garden) {
+        for (index, (_, region)) in garden.regions.iter().enumerate() {
+            for &position in region.positions.iter() {
+                assert_eq!(garden.region_at(position), Some(index));
+            }
+        }
+    }
+
+    #[test]
+    fn test_editing_splits_and_merges_regions() -> Result<(), Box<dyn Error>> {
+        let data_as_string = "AAA
+        BAB
+        AAA"
+        .replace("    ", "")
+        .to_string();
+        let mut editable_garden = EditableGarden::new(garden_regions_from_string(data_as_string));
+        assert_eq!(editable_garden.garden.regions.len(), 3);
+
+        editable_garden.set_plot(Position::new(1, 1), 'B')?;
+        assert_eq!(editable_garden.garden.regions.len(), 3);
+        assert_eq!(editable_garden.garden.total_price(), 3 * 8 * 2 + 3 * 8);
+        assert_labels_match_regions(&editable_garden.garden);
+
+        editable_garden.set_plot(Position::new(1, 1), 'A')?;
+        assert_eq!(editable_garden.garden.regions.len(), 3);
+        assert_labels_match_regions(&editable_garden.garden);
+
+        assert!(editable_garden.set_plot(Position::new(3, 0), 'A').is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_editing_matches_rebuilding() -> Result<(), Box<dyn Error>> {
+        let mut editable_garden = EditableGarden::new(garden_regions_from_string(get_test_data()));
+        let plants = ['R', 'I', 'C', 'F', 'X'];
+
+        let mut generator = SeededGenerator::new(12345);
+        for _ in 0..300 {
+            let position = Position::new(generator.below(10) as i32, generator.below(10) as i32);
+            let plant = plants[generator.below(plants.len() as u32) as usize];
+
+            editable_garden.set_plot(position, plant)?;
+
+            let rebuilt = Garden::from_garden_map(&editable_garden.garden_map);
+            assert_eq!(
+                region_summaries(&editable_garden.garden),
+                region_summaries(&rebuilt)
+            );
+            assert_labels_match_regions(&editable_garden.garden);
+        }
+        Ok(())
+    }
 }
@@ -1,30 +1,141 @@
+use itertools::Itertools;
 use regex::Regex;
 use std::error::Error;
+use std::fmt;
 
+// Every malformed block is reported, whether it fails to parse or is not a plain A/B machine.
 fn claw_machines_from_string(input: String) -> Result<Vec<ClawMachine>, Box<dyn Error>> {
-    let re = Regex::new(
-        r"Button A: X\+(\d+), Y\+(\d+)\n\s*Button B: X\+(\d+), Y\+(\d+)\n\s*Prize: X=(\d+), Y=(\d+)",
-    )?;
+    let (general_claw_machines, mut errors) = parse_claw_machine_blocks(&input)?;
+
     let mut data = Vec::new();
+    for (line_number, general_claw_machine) in general_claw_machines {
+        match ClawMachine::try_from(&general_claw_machine) {
+            Ok(claw_machine) => data.push(claw_machine),
+            Err(error) => errors.push((line_number, error.to_string())),
+        }
+    }
+    errors.sort_by_key(|&(line_number, _)| line_number);
+
+    if errors.is_empty() {
+        Ok(data)
+    } else {
+        Err(Box::new(ClawMachineParseErrors { errors }))
+    }
+}
+
+type ParsedClawMachines = (Vec<(usize, GeneralClawMachine)>, Vec<(usize, String)>);
 
-    for cap in re.captures_iter(&input) {
-        let button_a_x = cap[1].parse::<i64>()?;
-        let button_a_y = cap[2].parse::<i64>()?;
-        let button_b_x = cap[3].parse::<i64>()?;
-        let button_b_y = cap[4].parse::<i64>()?;
-        let prize_x = cap[5].parse::<i64>()?;
-        let prize_y = cap[6].parse::<i64>()?;
+// Parses every block, returning the machines with the line they start on next to the
+// errors of the blocks that could not be parsed.
+fn parse_claw_machine_blocks(input: &str) -> Result<ParsedClawMachines, regex::Error> {
+    let (button_re, prize_re) = claw_machine_regexes()?;
 
-        data.push(ClawMachine {
-            button_a: (button_a_x, button_a_y),
-            button_b: (button_b_x, button_b_y),
-            prize: (prize_x, prize_y),
-        });
+    let mut data = Vec::new();
+    let mut errors = Vec::new();
+    for block in claw_machine_blocks(input) {
+        match claw_machine_from_block(&block, &button_re, &prize_re) {
+            Ok(general_claw_machine) => data.push((block[0].0, general_claw_machine)),
+            Err(error) => errors.push(error),
+        }
     }
+    Ok((data, errors))
+}
 
-    Ok(data)
+fn claw_machine_regexes() -> Result<(Regex, Regex), regex::Error> {
+    Ok((
+        Regex::new(r"^Button (\w+): X([+-]\d+), Y([+-]\d+)(?:, Cost (\d+))?(?:, Max (\d+))?$")?,
+        Regex::new(r"^Prize: X=([+-]?\d+), Y=([+-]?\d+)$")?,
+    ))
+}
+
+// Blank-line separated blocks of (line number, trimmed line).
+fn claw_machine_blocks(input: &str) -> Vec<Vec<(usize, &str)>> {
+    let mut blocks: Vec<Vec<(usize, &str)>> = Vec::new();
+    let mut current_block = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !current_block.is_empty() {
+                blocks.push(std::mem::take(&mut current_block));
+            }
+        } else {
+            current_block.push((index + 1, line));
+        }
+    }
+    if !current_block.is_empty() {
+        blocks.push(current_block);
+    }
+    blocks
+}
+
+fn claw_machine_from_block(
+    block: &[(usize, &str)],
+    button_re: &Regex,
+    prize_re: &Regex,
+) -> Result<GeneralClawMachine, (usize, String)> {
+    let mut buttons = Vec::new();
+    let mut prize = None;
+
+    for &(line_number, line) in block {
+        let parse_number = |number: &str| {
+            number
+                .parse::<i64>()
+                .map_err(|error| (line_number, format!("invalid number {number}: {error}")))
+        };
+
+        if prize.is_some() {
+            return Err((line_number, "found a line after the prize".to_string()));
+        } else if let Some(cap) = button_re.captures(line) {
+            let name = cap[1].to_string();
+            let cost = match cap.get(4) {
+                Some(cost) => parse_number(cost.as_str())?,
+                None if name == "A" => 3,
+                None => 1,
+            };
+            let press_cap = match cap.get(5) {
+                Some(press_cap) => Some(parse_number(press_cap.as_str())?),
+                None => None,
+            };
+
+            buttons.push(Button {
+                offset: (parse_number(&cap[2])?, parse_number(&cap[3])?),
+                name,
+                cost,
+                press_cap,
+            });
+        } else if let Some(cap) = prize_re.captures(line) {
+            if buttons.is_empty() {
+                return Err((line_number, "found a prize before any button".to_string()));
+            }
+            prize = Some((parse_number(&cap[1])?, parse_number(&cap[2])?));
+        } else {
+            return Err((line_number, format!("unrecognised line \"{line}\"")));
+        }
+    }
+
+    match prize {
+        Some(prize) => Ok(GeneralClawMachine { buttons, prize }),
+        None => Err((block[0].0, "claw machine has no prize".to_string())),
+    }
 }
 
+#[derive(Debug)]
+struct ClawMachineParseErrors {
+    errors: Vec<(usize, String)>,
+}
+
+impl fmt::Display for ClawMachineParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Found {} malformed claw machine(s):", self.errors.len())?;
+        for (line_number, message) in self.errors.iter() {
+            write!(f, "\n  line {line_number}: {message}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ClawMachineParseErrors {}
+
 #[derive(Debug)]
 struct ClawMachine {
     button_a: (i64, i64),
@@ -32,55 +143,408 @@ struct ClawMachine {
     prize: (i64, i64),
 }
 
-impl ClawMachine {
-    fn get_price_to_solve(&self) -> Option<i64> {
-        let determinant = self.button_a.0 * self.button_b.1 - self.button_a.1 * self.button_b.0;
-
-        if determinant == 0 {
-            if self.prize.0 / self.button_a.0 != self.prize.1 / self.button_a.1 {
-                None
-            } else {
-                let mut possible_prices: Vec<i64> = Vec::new();
-                let mut number_of_button_a_presses = 0;
-                while self.button_a.0 * number_of_button_a_presses <= self.prize.0
-                    && self.button_a.1 * number_of_button_a_presses <= self.prize.1
-                {
-                    let mut prize_0 = self.button_a.0 * number_of_button_a_presses;
-                    let mut prize_1 = self.button_a.1 * number_of_button_a_presses;
-                    let mut number_of_button_b_presses = 0;
-                    while prize_0 <= self.prize.0 && prize_1 <= self.prize.1 {
-                        if prize_0 == self.prize.0 && prize_1 == self.prize.1 {
-                            possible_prices
-                                .push(3 * number_of_button_a_presses + number_of_button_b_presses);
-                        }
-                        prize_0 += self.button_b.0;
-                        prize_1 += self.button_b.1;
-                        number_of_button_b_presses += 1;
+impl TryFrom<&GeneralClawMachine> for ClawMachine {
+    type Error = Box<dyn Error>;
+
+    fn try_from(general_claw_machine: &GeneralClawMachine) -> Result<Self, Self::Error> {
+        match general_claw_machine.buttons.as_slice() {
+            [button_a, button_b]
+                if button_a.name == "A"
+                    && button_b.name == "B"
+                    && button_a.cost == 3
+                    && button_b.cost == 1
+                    && button_a.press_cap.is_none()
+                    && button_b.press_cap.is_none() =>
+            {
+                Ok(ClawMachine {
+                    button_a: button_a.offset,
+                    button_b: button_b.offset,
+                    prize: general_claw_machine.prize,
+                })
+            }
+            _ => Err("A claw machine must have exactly the buttons A and B.".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Button {
+    name: String,
+    offset: (i64, i64),
+    cost: i64,
+    press_cap: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct GeneralClawMachine {
+    buttons: Vec<Button>,
+    prize: (i64, i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GeneralPressPlan {
+    presses: Vec<i64>,
+    cost: i64,
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a.abs(), a.signum(), 0)
+    } else {
+        let (gcd, x, y) = extended_gcd(b, a % b);
+        (gcd, y, x - (a / b) * y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PressPlan {
+    a_presses: i64,
+    b_presses: i64,
+    cost: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnsolvableReason {
+    NonIntegerSolution,
+    NegativePresses,
+    ExceedsPressLimit,
+    ColinearWithoutLatticePoint,
+    CostOverflow,
+    UnboundedSearch,
+    SearchTooLarge,
+    NoCombinationReachesPrize,
+}
+
+impl fmt::Display for UnsolvableReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            UnsolvableReason::NonIntegerSolution => "needs a fractional number of presses",
+            UnsolvableReason::NegativePresses => "needs a negative number of presses",
+            UnsolvableReason::ExceedsPressLimit => "needs more presses than the limit allows",
+            UnsolvableReason::ColinearWithoutLatticePoint => {
+                "buttons are colinear and no combination hits the prize"
+            }
+            UnsolvableReason::CostOverflow => "costs more tokens than can be counted",
+            UnsolvableReason::UnboundedSearch => "has too many buttons without a press cap",
+            UnsolvableReason::SearchTooLarge => {
+                "has more press combinations than the search is allowed to try"
+            }
+            UnsolvableReason::NoCombinationReachesPrize => {
+                "no combination of button presses reaches the prize"
+            }
+        };
+        write!(f, "{description}")
+    }
+}
+
+#[derive(Debug, Default)]
+struct SolutionRange {
+    lowest_k: Option<i128>,
+    highest_k: Option<i128>,
+}
+
+impl SolutionRange {
+    // Restricts k so that start + k * delta stays non-negative.
+    fn require_non_negative(&mut self, start: i128, delta: i128) -> bool {
+        match delta.signum() {
+            1 => {
+                let bound = (-start).div_euclid(delta) + ((-start).rem_euclid(delta) != 0) as i128;
+                self.lowest_k = Some(self.lowest_k.map_or(bound, |k| k.max(bound)));
+            }
+            -1 => {
+                let bound = start.div_euclid(-delta);
+                self.highest_k = Some(self.highest_k.map_or(bound, |k| k.min(bound)));
+            }
+            _ if start < 0 => return false,
+            _ => {}
+        }
+        !matches!((self.lowest_k, self.highest_k), (Some(low), Some(high)) if low > high)
+    }
+}
+
+fn cheapest_non_negative_solution(
+    step_a: i128,
+    step_b: i128,
+    target: i128,
+    cost_a: i128,
+    cost_b: i128,
+    press_caps: (Option<i128>, Option<i128>),
+) -> Result<(i128, i128), UnsolvableReason> {
+    if step_a == 0 && step_b == 0 {
+        return if target == 0 {
+            Ok((0, 0))
+        } else {
+            Err(UnsolvableReason::ColinearWithoutLatticePoint)
+        };
+    }
+
+    let (gcd, x, y) = extended_gcd(step_a, step_b);
+    if target % gcd != 0 {
+        return Err(UnsolvableReason::ColinearWithoutLatticePoint);
+    }
+
+    // Every solution is (a0 + k * delta_a, b0 + k * delta_b) for an integer k.
+    let a0 = x * (target / gcd);
+    let b0 = y * (target / gcd);
+    let delta_a = step_b / gcd;
+    let delta_b = -step_a / gcd;
+
+    let mut range = SolutionRange::default();
+    if !(range.require_non_negative(a0, delta_a) && range.require_non_negative(b0, delta_b)) {
+        return Err(UnsolvableReason::NegativePresses);
+    }
+    let within_cap_a = press_caps
+        .0
+        .is_none_or(|cap| range.require_non_negative(cap - a0, -delta_a));
+    let within_cap_b = press_caps
+        .1
+        .is_none_or(|cap| range.require_non_negative(cap - b0, -delta_b));
+    if !(within_cap_a && within_cap_b) {
+        return Err(UnsolvableReason::ExceedsPressLimit);
+    }
+
+    // The cost is linear in k, so the cheapest solution sits at one end of the valid range.
+    let slope = cost_a * delta_a + cost_b * delta_b;
+    let k = if slope < 0 {
+        range.highest_k.or(range.lowest_k)
+    } else {
+        range.lowest_k.or(range.highest_k)
+    }
+    .unwrap_or(0);
+
+    Ok((a0 + k * delta_a, b0 + k * delta_b))
+}
+
+fn cheapest_two_button_presses(
+    button_a: (i128, i128),
+    button_b: (i128, i128),
+    prize: (i128, i128),
+    costs: (i128, i128),
+    press_caps: (Option<i128>, Option<i128>),
+) -> Result<(i128, i128), UnsolvableReason> {
+    let determinant = button_a.0 * button_b.1 - button_a.1 * button_b.0;
+
+    if determinant == 0 {
+        let direction = if button_a != (0, 0) {
+            button_a
+        } else {
+            button_b
+        };
+        if direction.0 * prize.1 - direction.1 * prize.0 != 0 {
+            return Err(UnsolvableReason::ColinearWithoutLatticePoint);
+        }
+
+        if button_a.0 != 0 || button_b.0 != 0 {
+            cheapest_non_negative_solution(
+                button_a.0, button_b.0, prize.0, costs.0, costs.1, press_caps,
+            )
+        } else if prize.0 == 0 {
+            cheapest_non_negative_solution(
+                button_a.1, button_b.1, prize.1, costs.0, costs.1, press_caps,
+            )
+        } else {
+            Err(UnsolvableReason::ColinearWithoutLatticePoint)
+        }
+    } else {
+        let numerator_a = prize.0 * button_b.1 - prize.1 * button_b.0;
+        let numerator_b = -prize.0 * button_a.1 + prize.1 * button_a.0;
+
+        if numerator_a % determinant != 0 || numerator_b % determinant != 0 {
+            return Err(UnsolvableReason::NonIntegerSolution);
+        }
+        let presses = (numerator_a / determinant, numerator_b / determinant);
+
+        if presses.0 < 0 || presses.1 < 0 {
+            return Err(UnsolvableReason::NegativePresses);
+        }
+        if press_caps.0.is_some_and(|cap| presses.0 > cap)
+            || press_caps.1.is_some_and(|cap| presses.1 > cap)
+        {
+            return Err(UnsolvableReason::ExceedsPressLimit);
+        }
+        Ok(presses)
+    }
+}
+
+impl GeneralClawMachine {
+    // Largest useful number of presses, known when the button cannot be undone by another one.
+    // A button that does not move the claw is never worth pressing.
+    fn press_bound(&self, button: &Button) -> Option<i128> {
+        if button.offset == (0, 0) {
+            return Some(0);
+        }
+        let axis_bound = |offset: i64, prize: i64, all_offsets_non_negative: bool| {
+            (all_offsets_non_negative && offset > 0).then(|| (prize.max(0) / offset) as i128)
+        };
+        let bound_x = axis_bound(
+            button.offset.0,
+            self.prize.0,
+            self.buttons.iter().all(|b| b.offset.0 >= 0),
+        );
+        let bound_y = axis_bound(
+            button.offset.1,
+            self.prize.1,
+            self.buttons.iter().all(|b| b.offset.1 >= 0),
+        );
+
+        [bound_x, bound_y, button.press_cap.map(|cap| cap as i128)]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    // All but two buttons are enumerated within their bounds, the remaining pair is solved
+    // exactly. The enumeration is a plain search, so machines with more than
+    // MAX_ENUMERATED_COMBINATIONS press combinations for the extra buttons are rejected
+    // instead of searched.
+    fn solve(&self) -> Result<GeneralPressPlan, UnsolvableReason> {
+        const MAX_ENUMERATED_COMBINATIONS: i128 = 10_000_000;
+
+        let mut buttons = self.buttons.clone();
+        while buttons.len() < 2 {
+            buttons.push(Button {
+                name: String::new(),
+                offset: (0, 0),
+                cost: 0,
+                press_cap: Some(0),
+            });
+        }
+
+        let bounds: Vec<Option<i128>> = buttons
+            .iter()
+            .map(|button| self.press_bound(button))
+            .collect();
+        let mut order: Vec<usize> = (0..buttons.len()).collect();
+        order.sort_by_key(|&index| bounds[index].unwrap_or(i128::MAX));
+        let (enumerated, exact) = order.split_at(buttons.len() - 2);
+
+        let enumerated_bounds = enumerated
+            .iter()
+            .map(|&index| bounds[index].ok_or(UnsolvableReason::UnboundedSearch))
+            .collect::<Result<Vec<i128>, UnsolvableReason>>()?;
+        let number_of_combinations = enumerated_bounds
+            .iter()
+            .try_fold(1_i128, |product, &bound| product.checked_mul(bound + 1))
+            .filter(|&combinations| combinations <= MAX_ENUMERATED_COMBINATIONS)
+            .ok_or(UnsolvableReason::SearchTooLarge)?;
+        let combinations: Box<dyn Iterator<Item = Vec<i128>>> = if enumerated.is_empty() {
+            Box::new(std::iter::once(Vec::new()))
+        } else {
+            Box::new(
+                enumerated_bounds
+                    .iter()
+                    .map(|&bound| 0..=bound)
+                    .multi_cartesian_product(),
+            )
+        };
+
+        let as_wide = |button: &Button| (button.offset.0 as i128, button.offset.1 as i128);
+        let (button_a, button_b) = (&buttons[exact[0]], &buttons[exact[1]]);
+
+        let mut cheapest: Option<(i128, Vec<i128>)> = None;
+        // With only two buttons the exact solver's reason explains the failure. Otherwise every
+        // combination can fail for a different reason, and none of them is the whole story.
+        let mut failure = None;
+        for combination in combinations {
+            let mut presses = vec![0; buttons.len()];
+            let mut remaining_prize = (self.prize.0 as i128, self.prize.1 as i128);
+            let mut cost = 0;
+            for (&index, &number_of_presses) in enumerated.iter().zip(combination.iter()) {
+                let offset = as_wide(&buttons[index]);
+                presses[index] = number_of_presses;
+                remaining_prize.0 -= offset.0 * number_of_presses;
+                remaining_prize.1 -= offset.1 * number_of_presses;
+                cost += buttons[index].cost as i128 * number_of_presses;
+            }
+
+            match cheapest_two_button_presses(
+                as_wide(button_a),
+                as_wide(button_b),
+                remaining_prize,
+                (button_a.cost as i128, button_b.cost as i128),
+                (
+                    button_a.press_cap.map(|cap| cap as i128),
+                    button_b.press_cap.map(|cap| cap as i128),
+                ),
+            ) {
+                Ok((presses_a, presses_b)) => {
+                    presses[exact[0]] = presses_a;
+                    presses[exact[1]] = presses_b;
+                    cost += button_a.cost as i128 * presses_a + button_b.cost as i128 * presses_b;
+                    if cheapest.as_ref().is_none_or(|(best, _)| cost < *best) {
+                        cheapest = Some((cost, presses));
                     }
-                    number_of_button_a_presses += 1;
                 }
-                if possible_prices.is_empty() {
-                    None
-                } else {
-                    possible_prices.into_iter().min()
+                Err(reason) => {
+                    failure = Some(match number_of_combinations {
+                        1 => reason,
+                        _ => UnsolvableReason::NoCombinationReachesPrize,
+                    });
                 }
             }
-        } else {
-            let number_of_button_a_presses =
-                (self.prize.0 * self.button_b.1 - self.prize.1 * self.button_b.0) / determinant;
-            let number_of_button_b_presses =
-                (-self.prize.0 * self.button_a.1 + self.prize.1 * self.button_a.0) / determinant;
-
-            let prize_0 = self.button_a.0 * number_of_button_a_presses
-                + self.button_b.0 * number_of_button_b_presses;
-            let prize_1 = self.button_a.1 * number_of_button_a_presses
-                + self.button_b.1 * number_of_button_b_presses;
-
-            if prize_0 == self.prize.0 && prize_1 == self.prize.1 {
-                Some(3 * number_of_button_a_presses + number_of_button_b_presses)
-            } else {
-                None
-            }
+        }
+
+        let (cost, mut presses) = match (cheapest, failure) {
+            (Some(cheapest), _) => cheapest,
+            (None, Some(reason)) => return Err(reason),
+            (None, None) => return Err(UnsolvableReason::NoCombinationReachesPrize),
+        };
+        presses.truncate(self.buttons.len());
+
+        let to_i64 = |value: i128| i64::try_from(value).map_err(|_| UnsolvableReason::CostOverflow);
+        Ok(GeneralPressPlan {
+            presses: presses
+                .into_iter()
+                .map(to_i64)
+                .collect::<Result<Vec<i64>, UnsolvableReason>>()?,
+            cost: to_i64(cost)?,
+        })
+    }
+}
+
+impl ClawMachine {
+    fn to_general(&self, press_limit: Option<i64>) -> GeneralClawMachine {
+        GeneralClawMachine {
+            buttons: vec![
+                Button {
+                    name: "A".to_string(),
+                    offset: self.button_a,
+                    cost: 3,
+                    press_cap: press_limit,
+                },
+                Button {
+                    name: "B".to_string(),
+                    offset: self.button_b,
+                    cost: 1,
+                    press_cap: press_limit,
+                },
+            ],
+            prize: self.prize,
+        }
+    }
+
+    fn solve(&self, press_limit: Option<i64>) -> Result<PressPlan, UnsolvableReason> {
+        let plan = self.to_general(press_limit).solve()?;
+        Ok(PressPlan {
+            a_presses: plan.presses[0],
+            b_presses: plan.presses[1],
+            cost: plan.cost,
+        })
+    }
+
+    fn get_price_to_solve(&self) -> Option<i64> {
+        self.solve(None).ok().map(|plan| plan.cost)
+    }
+}
+
+fn print_press_plans(claw_machines: &[ClawMachine], press_limit: Option<i64>) {
+    for (index, claw_machine) in claw_machines.iter().enumerate() {
+        match claw_machine.solve(press_limit) {
+            Ok(plan) => println!(
+                "Machine {index}: winnable with {} A presses and {} B presses for {} tokens",
+                plan.a_presses, plan.b_presses, plan.cost
+            ),
+            Err(reason) => println!("Machine {index}: not winnable, {reason}"),
         }
     }
 }
@@ -126,6 +590,27 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let claw_machines = claw_machines_from_string(data)?;
 
+    // "plans [press limit]" prints the press plan or the reason it is not winnable for every
+    // machine.
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    match arguments
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => {}
+        ["plans"] => {
+            print_press_plans(&claw_machines, None);
+            return Ok(());
+        }
+        ["plans", press_limit] => {
+            print_press_plans(&claw_machines, Some(press_limit.parse::<i64>()?));
+            return Ok(());
+        }
+        _ => return Err(format!("Unknown arguments {arguments:?}").into()),
+    }
+
     let result_part1 = part1(&claw_machines)?;
     let result_part2 = part2(&claw_machines)?;
 
@@ -175,4 +660,243 @@ mod tests {
         assert!(result == 483);
         Ok(())
     }
+
+    #[test]
+    fn test_colinear_buttons_match_brute_force() {
+        let colinear_buttons = [
+            ((2_i64, 4_i64), (1_i64, 2_i64), (1_i64, 2_i64)),
+            ((3, 6), (6, 12), (1, 2)),
+            ((5, 5), (1, 1), (1, 1)),
+            ((4, 4), (6, 6), (1, 1)),
+            ((4, 0), (7, 0), (1, 0)),
+            ((0, 3), (0, 2), (0, 1)),
+        ];
+
+        for (button_a, button_b, direction) in colinear_buttons {
+            for scale in 0..60 {
+                let prize = (direction.0 * scale, direction.1 * scale);
+                let claw_machine = ClawMachine {
+                    button_a,
+                    button_b,
+                    prize,
+                };
+
+                let mut brute_force: Option<i64> = None;
+                for a in 0..=60 {
+                    for b in 0..=60 {
+                        if button_a.0 * a + button_b.0 * b == prize.0
+                            && button_a.1 * a + button_b.1 * b == prize.1
+                        {
+                            let price = 3 * a + b;
+                            brute_force = Some(brute_force.map_or(price, |p| p.min(price)));
+                        }
+                    }
+                }
+
+                assert_eq!(claw_machine.get_price_to_solve(), brute_force);
+            }
+        }
+    }
+
+    #[test]
+    fn test_colinear_buttons_with_part2_offset() {
+        let claw_machine = ClawMachine {
+            button_a: (6, 6),
+            button_b: (4, 4),
+            prize: (10000000000002, 10000000000002),
+        };
+
+        assert_eq!(claw_machine.get_price_to_solve(), Some(2500000000002));
+    }
+
+    #[test]
+    fn test_press_plans_and_reasons() -> Result<(), Box<dyn Error>> {
+        let claw_machines = claw_machines_from_string(get_test_data())?;
+
+        assert_eq!(
+            claw_machines[0].solve(Some(100)),
+            Ok(PressPlan {
+                a_presses: 80,
+                b_presses: 40,
+                cost: 280
+            })
+        );
+        assert_eq!(
+            claw_machines[1].solve(Some(100)),
+            Ok(PressPlan {
+                a_presses: 1,
+                b_presses: 0,
+                cost: 3
+            })
+        );
+        assert_eq!(
+            claw_machines[2].solve(Some(100)),
+            Err(UnsolvableReason::NonIntegerSolution)
+        );
+
+        let far_away = ClawMachine {
+            button_a: (1, 0),
+            button_b: (0, 1),
+            prize: (101, 5),
+        };
+        assert_eq!(
+            far_away.solve(Some(100)),
+            Err(UnsolvableReason::ExceedsPressLimit)
+        );
+        assert!(far_away.solve(None).is_ok());
+
+        let behind = ClawMachine {
+            button_a: (2, 1),
+            button_b: (1, 2),
+            prize: (-3, -3),
+        };
+        assert_eq!(behind.solve(None), Err(UnsolvableReason::NegativePresses));
+
+        let off_lattice = ClawMachine {
+            button_a: (4, 4),
+            button_b: (6, 6),
+            prize: (7, 7),
+        };
+        assert_eq!(
+            off_lattice.solve(None),
+            Err(UnsolvableReason::ColinearWithoutLatticePoint)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_general_claw_machine_matches_brute_force() -> Result<(), Box<dyn Error>> {
+        let data_as_string = "Button A: X+3, Y+1, Cost 3
+        Button B: X+1, Y+2, Cost 1
+        Button C: X+2, Y+2, Cost 2, Max 4
+        Prize: X=20, Y=18
+
+        Button A: X+4, Y+4
+        Button B: X+1, Y+3
+        Button C: X+3, Y+1, Cost 1
+        Prize: X=17, Y=19"
+            .replace("    ", "")
+            .to_string();
+
+        let (claw_machines, errors) = parse_claw_machine_blocks(&data_as_string)?;
+        assert!(errors.is_empty());
+        let claw_machines: Vec<GeneralClawMachine> = claw_machines
+            .into_iter()
+            .map(|(_, claw_machine)| claw_machine)
+            .collect();
+        assert_eq!(claw_machines.len(), 2);
+        assert_eq!(claw_machines[0].buttons[2].press_cap, Some(4));
+        assert_eq!(claw_machines[1].buttons[0].cost, 3);
+
+        for claw_machine in claw_machines.iter() {
+            let mut brute_force: Option<i64> = None;
+            for presses in (0..3).map(|_| 0..=20_i64).multi_cartesian_product() {
+                let reaches_prize = (0..3).fold((0, 0), |sum, index| {
+                    let offset = claw_machine.buttons[index].offset;
+                    (
+                        sum.0 + offset.0 * presses[index],
+                        sum.1 + offset.1 * presses[index],
+                    )
+                }) == claw_machine.prize;
+                let within_caps = (0..3).all(|index| {
+                    claw_machine.buttons[index]
+                        .press_cap
+                        .is_none_or(|cap| presses[index] <= cap)
+                });
+                if reaches_prize && within_caps {
+                    let cost = (0..3)
+                        .map(|index| claw_machine.buttons[index].cost * presses[index])
+                        .sum();
+                    brute_force = Some(brute_force.map_or(cost, |c: i64| c.min(cost)));
+                }
+            }
+
+            assert_eq!(claw_machine.solve().ok().map(|plan| plan.cost), brute_force);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_accepts_signed_offsets_and_crlf() -> Result<(), Box<dyn Error>> {
+        let data_as_string =
+            "Button A: X+94, Y-34\r\nButton B: X-22, Y+67\r\nPrize: X=8400, Y=-5400\r\n\r\n\
+            Button A: X+5, Y+5\r\nButton B: X+1, Y+1\r\nPrize: X=5, Y=5\r\n"
+                .to_string();
+
+        let claw_machines = claw_machines_from_string(data_as_string)?;
+
+        assert_eq!(claw_machines.len(), 2);
+        assert_eq!(claw_machines[0].button_a, (94, -34));
+        assert_eq!(claw_machines[0].button_b, (-22, 67));
+        assert_eq!(claw_machines[0].prize, (8400, -5400));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_reports_malformed_machines() {
+        let data_as_string = "Button A: X+94, Y+34
+        Button B: X+22, Y+67
+        Prize: X=8400, Y=5400
+
+        Button A: X+5, Y+5
+        Buton B: X+1, Y+1
+        Prize: X=5, Y=5
+
+        Button A: X+26, Y+66
+        Button B: X+67, Y+21
+
+        Button A: X+17, Y+86
+        Button C: X+84, Y+37
+        Prize: X=7870, Y=6450"
+            .replace("    ", "")
+            .to_string();
+
+        let error = claw_machines_from_string(data_as_string)
+            .err()
+            .map(|error| error.to_string());
+
+        assert_eq!(
+            error.as_deref(),
+            Some(
+                "Found 3 malformed claw machine(s):\n  \
+                line 6: unrecognised line \"Buton B: X+1, Y+1\"\n  \
+                line 9: claw machine has no prize\n  \
+                line 12: A claw machine must have exactly the buttons A and B."
+            )
+        );
+    }
+
+    #[test]
+    fn test_general_claw_machine_search_limits() -> Result<(), Box<dyn Error>> {
+        let data_as_string = "Button A: X+94, Y+34
+        Button B: X+22, Y+67
+        Button C: X+0, Y+0, Cost 0
+        Prize: X=8400, Y=5400
+
+        Button A: X+94, Y+34
+        Button B: X+22, Y+67
+        Button C: X+3, Y+5, Cost 2
+        Prize: X=10000000008400, Y=10000000005400
+
+        Button A: X+2, Y+2
+        Button B: X+4, Y+4
+        Button C: X+6, Y+6, Cost 2
+        Prize: X=7, Y=7"
+            .replace("    ", "")
+            .to_string();
+
+        let (claw_machines, errors) = parse_claw_machine_blocks(&data_as_string)?;
+        assert!(errors.is_empty());
+
+        assert_eq!(claw_machines[0].1.solve().map(|plan| plan.cost), Ok(280));
+        assert_eq!(
+            claw_machines[1].1.solve(),
+            Err(UnsolvableReason::SearchTooLarge)
+        );
+        assert_eq!(
+            claw_machines[2].1.solve(),
+            Err(UnsolvableReason::NoCombinationReachesPrize)
+        );
+        Ok(())
+    }
 }
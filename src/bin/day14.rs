@@ -1,3 +1,4 @@
+use rayon::prelude::*;
 use regex::Regex;
 use std::error::Error;
 
@@ -26,44 +27,102 @@ struct Robot {
     velocity: (i32, i32),
 }
 
-impl Robot {
+struct RobotSpace {
+    size: (i32, i32),
+}
+
+#[derive(Debug, Clone)]
+struct RobotSwarm {
+    x: Vec<i32>,
+    y: Vec<i32>,
+    vx: Vec<i32>,
+    vy: Vec<i32>,
+}
+
+impl RobotSwarm {
+    fn from_robots(robots: &[Robot], robot_space: &RobotSpace) -> RobotSwarm {
+        // Positions and velocities are stored wrapped into [0, size) so a single step only
+        // needs an add and a conditional subtract instead of two modulo operations per axis.
+        RobotSwarm {
+            x: robots
+                .iter()
+                .map(|robot| robot.position.0.rem_euclid(robot_space.size.0))
+                .collect(),
+            y: robots
+                .iter()
+                .map(|robot| robot.position.1.rem_euclid(robot_space.size.1))
+                .collect(),
+            vx: robots
+                .iter()
+                .map(|robot| robot.velocity.0.rem_euclid(robot_space.size.0))
+                .collect(),
+            vy: robots
+                .iter()
+                .map(|robot| robot.velocity.1.rem_euclid(robot_space.size.1))
+                .collect(),
+        }
+    }
+
+    fn step(&mut self, robot_space: &RobotSpace) {
+        step_axis(&mut self.x, &self.vx, robot_space.size.0);
+        step_axis(&mut self.y, &self.vy, robot_space.size.1);
+    }
+
     fn evolve(&mut self, time: i32, robot_space: &RobotSpace) {
-        self.position = (
-            ((self.position.0 + self.velocity.0 * time) % robot_space.size.0 + robot_space.size.0)
-                % robot_space.size.0,
-            ((self.position.1 + self.velocity.1 * time) % robot_space.size.1 + robot_space.size.1)
-                % robot_space.size.1,
-        );
+        evolve_axis(&mut self.x, &self.vx, time, robot_space.size.0);
+        evolve_axis(&mut self.y, &self.vy, time, robot_space.size.1);
+    }
+
+    fn positions(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.x.iter().copied().zip(self.y.iter().copied())
     }
 }
 
-struct RobotSpace {
-    size: (i32, i32),
+fn step_axis(positions: &mut [i32], velocities: &[i32], size: i32) {
+    for (position, &velocity) in positions.iter_mut().zip(velocities) {
+        let moved = *position + velocity;
+        *position = if moved >= size { moved - size } else { moved };
+    }
+}
+
+fn evolve_axis(positions: &mut [i32], velocities: &[i32], time: i32, size: i32) {
+    let time = time.rem_euclid(size) as i64;
+    for (position, &velocity) in positions.iter_mut().zip(velocities) {
+        *position = ((*position as i64 + velocity as i64 * time) % size as i64) as i32;
+    }
 }
 
 fn import_data() -> Result<String, Box<dyn Error>> {
     Ok(std::fs::read_to_string("data/day14.txt")?)
 }
 
-fn calculate_heurestic_from_robots(robots: &[Robot], robot_space: &RobotSpace) -> i32 {
-    let mut number_of_robots_in_top_left = 0;
-    let mut number_of_robots_in_top_right = 0;
-    let mut number_of_robots_in_buttom_left = 0;
-    let mut number_of_robots_in_buttom_right = 0;
-
+fn calculate_heurestic_from_robots(robots: &RobotSwarm, robot_space: &RobotSpace) -> i32 {
     let mid_size = ((robot_space.size.0 - 1) / 2, (robot_space.size.1 - 1) / 2);
 
-    for robot in robots.iter() {
-        if robot.position.0 < mid_size.0 && robot.position.1 < mid_size.1 {
-            number_of_robots_in_top_left += 1;
-        } else if robot.position.0 > mid_size.0 && robot.position.1 < mid_size.1 {
-            number_of_robots_in_top_right += 1;
-        } else if robot.position.0 < mid_size.0 && robot.position.1 > mid_size.1 {
-            number_of_robots_in_buttom_left += 1;
-        } else if robot.position.0 > mid_size.0 && robot.position.1 > mid_size.1 {
-            number_of_robots_in_buttom_right += 1;
-        }
-    }
+    let [number_of_robots_in_top_left, number_of_robots_in_top_right, number_of_robots_in_buttom_left, number_of_robots_in_buttom_right] =
+        robots
+            .x
+            .par_iter()
+            .zip(robots.y.par_iter())
+            .fold(
+                || [0; 4],
+                |mut quadrants, (&x, &y)| {
+                    if x < mid_size.0 && y < mid_size.1 {
+                        quadrants[0] += 1;
+                    } else if x > mid_size.0 && y < mid_size.1 {
+                        quadrants[1] += 1;
+                    } else if x < mid_size.0 && y > mid_size.1 {
+                        quadrants[2] += 1;
+                    } else if x > mid_size.0 && y > mid_size.1 {
+                        quadrants[3] += 1;
+                    }
+                    quadrants
+                },
+            )
+            .reduce(
+                || [0; 4],
+                |a, b| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]],
+            );
 
     number_of_robots_in_top_left
         * number_of_robots_in_top_right
@@ -71,39 +130,44 @@ fn calculate_heurestic_from_robots(robots: &[Robot], robot_space: &RobotSpace) -
         * number_of_robots_in_buttom_right
 }
 
-fn calculate_new_heurestic_from_robots(robots: &[Robot], _robot_space: &RobotSpace) -> i64 {
-    let number_of_positions = robots.len() as i64;
+// Below this many robots handing the variance to the thread pool costs more than it saves.
+const MIN_ROBOTS_PER_THREAD: usize = 50_000;
 
-    let mean_of_x: i64 = robots
-        .iter()
-        .map(|robot| robot.position.0 as i64)
-        .sum::<i64>()
-        / number_of_positions;
-    let mean_of_y: i64 = robots
-        .iter()
-        .map(|robot| robot.position.1 as i64)
+fn variance_of_axis(positions: &[i32]) -> i64 {
+    let number_of_positions = positions.len() as i64;
+    let mean = positions
+        .par_iter()
+        .with_min_len(MIN_ROBOTS_PER_THREAD)
+        .map(|&p| p as i64)
         .sum::<i64>()
         / number_of_positions;
 
-    let var_of_x: i64 = robots
-        .iter()
-        .map(|robot| (robot.position.0 as i64 - mean_of_x).pow(2))
+    positions
+        .par_iter()
+        .with_min_len(MIN_ROBOTS_PER_THREAD)
+        .map(|&p| (p as i64 - mean).pow(2))
         .sum::<i64>()
-        / number_of_positions;
-    let var_of_y: i64 = robots
-        .iter()
-        .map(|robot| (robot.position.1 as i64 - mean_of_y).pow(2))
-        .sum::<i64>()
-        / number_of_positions;
+        / number_of_positions
+}
+
+fn calculate_new_heurestic_from_robots(robots: &RobotSwarm, _robot_space: &RobotSpace) -> i64 {
+    let (var_of_x, var_of_y) = if robots.x.len() < MIN_ROBOTS_PER_THREAD {
+        (variance_of_axis(&robots.x), variance_of_axis(&robots.y))
+    } else {
+        rayon::join(
+            || variance_of_axis(&robots.x),
+            || variance_of_axis(&robots.y),
+        )
+    };
 
     var_of_x + var_of_y
 }
 
-fn print_robots(robots: &Vec<Robot>, robot_space: &RobotSpace) {
+fn print_robots(robots: &RobotSwarm, robot_space: &RobotSpace) {
     let mut grid = vec![vec!['.'; robot_space.size.0 as usize]; robot_space.size.1 as usize];
 
-    for robot in robots {
-        grid[robot.position.1 as usize][robot.position.0 as usize] = '#';
+    for (x, y) in robots.positions() {
+        grid[y as usize][x as usize] = '#';
     }
 
     for row in grid {
@@ -111,21 +175,17 @@ fn print_robots(robots: &Vec<Robot>, robot_space: &RobotSpace) {
     }
 }
 
-fn part1(mut robots: Vec<Robot>, robot_space: &RobotSpace) -> Result<i32, Box<dyn Error>> {
-    for robot in robots.iter_mut() {
-        robot.evolve(100, robot_space);
-    }
+fn part1(mut robots: RobotSwarm, robot_space: &RobotSpace) -> Result<i32, Box<dyn Error>> {
+    robots.evolve(100, robot_space);
     Ok(calculate_heurestic_from_robots(&robots, robot_space))
 }
 
-fn part2(mut robots: Vec<Robot>, robot_space: &RobotSpace) -> Result<i32, Box<dyn Error>> {
+fn part2(mut robots: RobotSwarm, robot_space: &RobotSpace) -> Result<i32, Box<dyn Error>> {
     let mut heurestics = Vec::new();
     let mut clone_of_robots = robots.clone();
 
     for _ in 0..100000 {
-        for robot in robots.iter_mut() {
-            robot.evolve(1, robot_space);
-        }
+        robots.step(robot_space);
         let heurestic = calculate_new_heurestic_from_robots(&robots, robot_space);
         heurestics.push(heurestic);
     }
@@ -136,9 +196,7 @@ fn part2(mut robots: Vec<Robot>, robot_space: &RobotSpace) -> Result<i32, Box<dy
         .unwrap() as i32
         + 1;
 
-    for robot in clone_of_robots.iter_mut() {
-        robot.evolve(time_at_min_heurestic, robot_space);
-    }
+    clone_of_robots.evolve(time_at_min_heurestic, robot_space);
     println!("Robots at time={}\n", time_at_min_heurestic);
     print_robots(&clone_of_robots, robot_space);
 
@@ -148,8 +206,8 @@ fn part2(mut robots: Vec<Robot>, robot_space: &RobotSpace) -> Result<i32, Box<dy
 fn main() -> Result<(), Box<dyn Error>> {
     let data = import_data()?;
 
-    let robots = robots_from_string(data)?;
     let robot_space = RobotSpace { size: (101, 103) };
+    let robots = RobotSwarm::from_robots(&robots_from_string(data)?, &robot_space);
 
     let result_part1 = part1(robots.clone(), &robot_space)?;
     let result_part2 = part2(robots.clone(), &robot_space)?;
@@ -186,7 +244,7 @@ mod tests {
         let data_as_string = get_test_data();
         let robot_space = RobotSpace { size: (11, 7) };
 
-        let robots = robots_from_string(data_as_string)?;
+        let robots = RobotSwarm::from_robots(&robots_from_string(data_as_string)?, &robot_space);
 
         let result = part1(robots, &robot_space)?;
 
@@ -194,4 +252,42 @@ mod tests {
         assert!(result == 12);
         Ok(())
     }
+
+    #[test]
+    fn test_swarm_matches_per_robot_evolution() -> Result<(), Box<dyn Error>> {
+        let robot_space = RobotSpace { size: (11, 7) };
+        let robots = robots_from_string(get_test_data())?;
+
+        let mut stepped = RobotSwarm::from_robots(&robots, &robot_space);
+        let mut evolved = stepped.clone();
+        for time in 1..=250 {
+            stepped.step(&robot_space);
+            evolved.evolve(1, &robot_space);
+
+            let expected: Vec<(i32, i32)> = robots
+                .iter()
+                .map(|robot| {
+                    (
+                        ((robot.position.0 + robot.velocity.0 * time) % robot_space.size.0
+                            + robot_space.size.0)
+                            % robot_space.size.0,
+                        ((robot.position.1 + robot.velocity.1 * time) % robot_space.size.1
+                            + robot_space.size.1)
+                            % robot_space.size.1,
+                    )
+                })
+                .collect();
+
+            assert_eq!(stepped.positions().collect::<Vec<_>>(), expected);
+            assert_eq!(evolved.positions().collect::<Vec<_>>(), expected);
+        }
+
+        // Positions outside the space are wrapped like the old per-robot evolution did.
+        let outside = robots_from_string("p=-1,9 v=0,0\np=12,-8 v=1,1".to_string())?;
+        let mut swarm = RobotSwarm::from_robots(&outside, &robot_space);
+        assert_eq!(swarm.positions().collect::<Vec<_>>(), vec![(10, 2), (1, 6)]);
+        swarm.step(&robot_space);
+        assert_eq!(swarm.positions().collect::<Vec<_>>(), vec![(10, 2), (2, 0)]);
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use advent_of_code_2024::digits;
 use std::error::Error;
 use std::num::ParseIntError;
 
@@ -16,20 +17,21 @@ fn extract_elements_from_string(data: String) -> Result<Vec<(i64, Vec<i64>)>, Pa
         .collect()
 }
 
+// Results that overflow are dropped, as the values only grow and can never match the target.
 fn calculate_plus_and_times(left: Vec<i64>, right: i64) -> Vec<i64> {
-    let mut result: Vec<i64> = left.iter().map(|l| l + right).collect();
-    result.extend(left.iter().map(|l| l * right));
+    let mut result: Vec<i64> = left.iter().filter_map(|l| l.checked_add(right)).collect();
+    result.extend(left.iter().filter_map(|l| l.checked_mul(right)));
     result
 }
 
-fn concatenate_digits(left: i64, right: i64) -> i64 {
-    left * (10_i64.pow(right.to_string().len() as u32)) + right
+fn concatenate_digits(left: i64, right: i64) -> Option<i64> {
+    let concatenated = digits::concatenate(u64::try_from(left).ok()?, u64::try_from(right).ok()?)?;
+    i64::try_from(concatenated).ok()
 }
 
 fn calculate_plus_times_and_concatenation(left: Vec<i64>, right: i64) -> Vec<i64> {
-    let mut result: Vec<i64> = left.iter().map(|l| l + right).collect();
-    result.extend(left.iter().map(|l| l * right));
-    result.extend(left.iter().map(|&l| concatenate_digits(l, right)));
+    let mut result = calculate_plus_and_times(left.clone(), right);
+    result.extend(left.iter().filter_map(|&l| concatenate_digits(l, right)));
     result
 }
 
@@ -122,4 +124,15 @@ mod tests {
         assert!(result == 11387);
         Ok(())
     }
+
+    #[test]
+    fn test_overflowing_results_are_dropped() -> Result<(), Box<dyn Error>> {
+        let input_data = extract_elements_from_string(
+            "1: 999999999 999999999 999999999\n18: 9 9 9223372036854775807".to_string(),
+        )?;
+
+        assert_eq!(part1(&input_data)?, 0);
+        assert_eq!(part2(&input_data)?, 0);
+        Ok(())
+    }
 }
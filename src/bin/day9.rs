@@ -1,144 +1,869 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
 use std::error::Error;
+use std::fmt;
 
-use itertools::Itertools;
+// An optional second line lists the id of every file digit, for disk maps whose file ids
+// do not simply count up from 0 such as serialised compaction results.
+fn file_blocks_from_string(data: String) -> Result<FileSystem, DiskMapParseError> {
+    let leading_whitespace = data.len() - data.trim_start().len();
+    let (disk_map, file_ids) = match data.trim().split_once('\n') {
+        Some((disk_map, file_ids)) => (disk_map.trim_end(), Some(file_ids)),
+        None => (data.trim(), None),
+    };
+    if disk_map.is_empty() {
+        return Err(DiskMapParseError::Empty);
+    }
+
+    let number_of_file_digits = disk_map.len().div_ceil(2);
+    let file_ids: Vec<u64> = match file_ids {
+        Some(file_ids) => file_ids
+            .split_whitespace()
+            .map(|id| {
+                id.parse::<u64>()
+                    .map_err(|_| DiskMapParseError::InvalidFileId(id.to_string()))
+            })
+            .collect::<Result<Vec<u64>, DiskMapParseError>>()?,
+        None => (0..number_of_file_digits as u64).collect(),
+    };
 
-fn file_blocks_from_string(data: String) -> FileSystem {
     let mut data_blocks: Vec<Option<File>> = Vec::new();
-    for (index, character) in data.char_indices() {
-        let number = character.to_digit(10).expect("A digit 0-9.") as i32;
-        if index % 2 == 0 {
-            data_blocks.extend((0..number).map(|_| {
+    for (index, (byte_index, character)) in disk_map.char_indices().enumerate() {
+        let number = character.to_digit(10).ok_or(DiskMapParseError::NotADigit {
+            position: leading_whitespace + byte_index,
+            character,
+        })? as usize;
+        if index == 0 && number == 0 {
+            return Err(DiskMapParseError::StartsWithFreeSpan);
+        }
+        match index % 2 {
+            0 => data_blocks.extend(std::iter::repeat_n(
                 Some(File {
-                    id: (index as i32) / 2,
-                })
-            }));
-        } else {
-            data_blocks.extend((0..number).map(|_| None));
+                    id: *file_ids
+                        .get(index / 2)
+                        .ok_or(DiskMapParseError::FileIdCountMismatch {
+                            file_digits: number_of_file_digits,
+                            file_ids: file_ids.len(),
+                        })?,
+                }),
+                number,
+            )),
+            _ => data_blocks.extend(std::iter::repeat_n(None, number)),
         }
     }
-    FileSystem {
+    if file_ids.len() != number_of_file_digits {
+        return Err(DiskMapParseError::FileIdCountMismatch {
+            file_digits: number_of_file_digits,
+            file_ids: file_ids.len(),
+        });
+    }
+
+    Ok(FileSystem {
         memory: data_blocks,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DiskMapParseError {
+    Empty,
+    // Position is the byte offset in the untrimmed input.
+    NotADigit { position: usize, character: char },
+    StartsWithFreeSpan,
+    InvalidFileId(String),
+    FileIdCountMismatch { file_digits: usize, file_ids: usize },
+}
+
+impl fmt::Display for DiskMapParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiskMapParseError::Empty => write!(f, "The disk map is empty"),
+            DiskMapParseError::NotADigit {
+                position,
+                character,
+            } => write!(
+                f,
+                "Expected a digit 0-9 at position {position}, found {character:?}"
+            ),
+            DiskMapParseError::StartsWithFreeSpan => {
+                write!(f, "The disk map starts with free space instead of a file")
+            }
+            DiskMapParseError::InvalidFileId(id) => write!(f, "Invalid file id {id:?}"),
+            DiskMapParseError::FileIdCountMismatch {
+                file_digits,
+                file_ids,
+            } => write!(
+                f,
+                "The disk map has {file_digits} file digits but {file_ids} file ids"
+            ),
+        }
+    }
+}
+
+impl Error for DiskMapParseError {}
+
+// The checksum of a disk with large file ids does not fit in an i64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChecksumOverflow;
+
+impl fmt::Display for ChecksumOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The checksum does not fit in a 64-bit integer")
     }
 }
 
+impl Error for ChecksumOverflow {}
+
+// Adds id * position_sum to the checksum, failing instead of wrapping.
+fn add_to_checksum(sum: i64, id: u64, position_sum: usize) -> Result<i64, ChecksumOverflow> {
+    let id = i64::try_from(id).map_err(|_| ChecksumOverflow)?;
+    let position_sum = i64::try_from(position_sum).map_err(|_| ChecksumOverflow)?;
+    id.checked_mul(position_sum)
+        .and_then(|product| sum.checked_add(product))
+        .ok_or(ChecksumOverflow)
+}
+
 fn import_data() -> Result<String, Box<dyn Error>> {
     Ok(std::fs::read_to_string("data/day9.txt")?)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct File {
-    id: i32,
+    id: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct FileSystem {
     memory: Vec<Option<File>>,
 }
 
 impl FileSystem {
-    fn is_fully_sorted(&self) -> bool {
-        let mut end_of_system = false;
-        for memory_block in self.memory.iter() {
-            match (end_of_system, memory_block) {
-                (true, Some(_)) => return false,
-                (false, None) => end_of_system = true,
-                _ => {}
+    // Writes the disk back as alternating file and free run lengths. Runs longer than 9 are
+    // split by empty runs of the other kind, and the file id line is only added when the
+    // ids differ from the ones the parser counts up by itself.
+    fn to_disk_map(&self) -> Result<String, Box<dyn Error>> {
+        if self.memory.first().is_some_and(Option::is_none) {
+            return Err("A disk map cannot start with free space".into());
+        }
+
+        let mut digits = String::new();
+        let mut file_ids: Vec<u64> = Vec::new();
+        for run in self.memory.chunk_by(|a, b| a == b) {
+            let file = run[0];
+            for (chunk_index, chunk) in run.chunks(9).enumerate() {
+                let is_file_digit = digits.len().is_multiple_of(2);
+                if chunk_index > 0 || is_file_digit != file.is_some() {
+                    // An empty file takes the id the parser would give it anyway.
+                    if is_file_digit {
+                        file_ids.push(file_ids.len() as u64);
+                    }
+                    digits.push('0');
+                }
+                if let Some(file) = file {
+                    file_ids.push(file.id);
+                }
+                digits.push(char::from_digit(chunk.len() as u32, 10).unwrap_or('?'));
             }
         }
-        true
+
+        if file_ids.iter().zip(0..).all(|(&id, index)| id == index) {
+            return Ok(digits);
+        }
+        let file_ids: Vec<String> = file_ids.iter().map(u64::to_string).collect();
+        Ok(format!("{digits}\n{}", file_ids.join(" ")))
     }
 
-    fn memory_length(&self) -> usize {
-        self.memory.len()
+    // Serialises the disk and checks that parsing the result gives back the same disk.
+    fn to_verified_disk_map(&self) -> Result<String, Box<dyn Error>> {
+        let disk_map = self.to_disk_map()?;
+        if file_blocks_from_string(disk_map.clone())? != *self {
+            return Err("The serialised disk map does not parse back to the same disk".into());
+        }
+        Ok(disk_map)
     }
 
-    fn sort_last_file_block_to_first_empty(&mut self) -> Result<(), Box<dyn Error>> {
-        let memory_length = self.memory_length();
-        let index_of_first_none: Option<usize> = self
-            .memory
+    // One character per block like the puzzle does, so file ids above 9 wrap around to
+    // their last digit.
+    fn render(&self) -> String {
+        self.memory
             .iter()
-            .position(|memory_block| memory_block.is_none());
-        let index_of_final_file_block_from_right = self
+            .map(|block| match block {
+                Some(file) => char::from_digit((file.id % 10) as u32, 10).unwrap_or('?'),
+                None => '.',
+            })
+            .collect()
+    }
+
+    // One pixel per block in rows of `width` blocks. Free blocks are black and every file
+    // gets its own hue.
+    fn to_png(&self, width: usize) -> Vec<u8> {
+        let height = self.memory.len().div_ceil(width).max(1);
+        let mut pixels: Vec<[u8; 3]> = self
             .memory
             .iter()
-            .rev()
-            .position(|memory_block| memory_block.is_some());
+            .map(|block| match block {
+                Some(file) => hue_to_rgb((file.id % 360 * 137 % 360) as u32),
+                None => [0, 0, 0],
+            })
+            .collect();
+        pixels.resize(width * height, [0, 0, 0]);
+        encode_png(width as u32, height as u32, &pixels)
+    }
+
+    fn calculate_checksum(&self) -> Result<i64, ChecksumOverflow> {
+        self.memory
+            .iter()
+            .enumerate()
+            .try_fold(0, |sum, (index, file_block)| match file_block {
+                Some(f) => add_to_checksum(sum, f.id, index),
+                None => Ok(sum),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    file: File,
+    start: usize,
+    length: usize,
+}
+
+impl Extent {
+    fn end(&self) -> usize {
+        self.start + self.length
+    }
+}
+
+// Blocks from..from + length of a file relocated to to..to + length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    file: File,
+    from: usize,
+    to: usize,
+    length: usize,
+}
 
-        match (index_of_first_none, index_of_final_file_block_from_right) {
-            (Some(idx1), Some(idx2)) => {
-                self.memory.swap(idx1, memory_length - idx2 - 1);
-                Ok(())
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FreeSpan {
+    start: usize,
+    length: usize,
+}
+
+// The disk as runs of blocks instead of single blocks. Both lists are sorted by start, and
+// a file split by block compaction has one extent per fragment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExtentDisk {
+    extents: Vec<Extent>,
+    free_spans: Vec<FreeSpan>,
+    size: usize,
+}
+
+impl ExtentDisk {
+    fn from_file_system(file_system: &FileSystem) -> ExtentDisk {
+        let mut extents: Vec<Extent> = Vec::new();
+        let mut free_spans: Vec<FreeSpan> = Vec::new();
+        for (start, block) in file_system.memory.iter().enumerate() {
+            match block {
+                Some(file) => match extents.last_mut() {
+                    Some(extent) if extent.file == *file && extent.end() == start => {
+                        extent.length += 1
+                    }
+                    _ => extents.push(Extent {
+                        file: *file,
+                        start,
+                        length: 1,
+                    }),
+                },
+                None => match free_spans.last_mut() {
+                    Some(span) if span.start + span.length == start => span.length += 1,
+                    _ => free_spans.push(FreeSpan { start, length: 1 }),
+                },
             }
-            _ => Err("File must contain both None and Files.".into()),
+        }
+        ExtentDisk {
+            extents,
+            free_spans,
+            size: file_system.memory.len(),
         }
     }
 
-    fn move_whole_file_to_first_empty(&mut self, file: File) -> Result<(), Box<dyn Error>> {
-        let file_memory_indices: Vec<usize> = self
-            .memory
+    fn to_file_system(&self) -> FileSystem {
+        let mut memory: Vec<Option<File>> = vec![None; self.size];
+        for extent in &self.extents {
+            memory[extent.start..extent.end()].fill(Some(extent.file));
+        }
+        FileSystem { memory }
+    }
+
+    // Sorts the extents and derives the free spans as the gaps between them.
+    fn rebuild_free_spans(&mut self) {
+        self.extents.sort_by_key(|extent| extent.start);
+        self.free_spans.clear();
+        let mut position = 0;
+        for extent in &self.extents {
+            if extent.start > position {
+                self.free_spans.push(FreeSpan {
+                    start: position,
+                    length: extent.start - position,
+                });
+            }
+            position = extent.end();
+        }
+        if self.size > position {
+            self.free_spans.push(FreeSpan {
+                start: position,
+                length: self.size - position,
+            });
+        }
+    }
+
+    // Moves the last file blocks into the first free blocks until no free block is left
+    // before a file block, taking whole runs at a time.
+    fn compact_blocks(&mut self) -> Vec<Move> {
+        let mut remaining_files = std::mem::take(&mut self.extents);
+        let mut moved: Vec<Extent> = Vec::new();
+        let mut free_spans = self
+            .free_spans
+            .iter()
+            .copied()
+            .filter(|span| span.length > 0);
+        let mut free_span = free_spans.next();
+        let mut moves: Vec<Move> = Vec::new();
+
+        while let Some(mut extent) = remaining_files.pop() {
+            let Some(span) = free_span.as_mut().filter(|span| span.start < extent.start) else {
+                remaining_files.push(extent);
+                break;
+            };
+
+            let moved_length = span.length.min(extent.length);
+            moves.push(Move {
+                file: extent.file,
+                from: extent.end() - moved_length,
+                to: span.start,
+                length: moved_length,
+            });
+            moved.push(Extent {
+                file: extent.file,
+                start: span.start,
+                length: moved_length,
+            });
+            span.start += moved_length;
+            span.length -= moved_length;
+            extent.length -= moved_length;
+
+            if span.length == 0 {
+                free_span = free_spans.next();
+            }
+            if extent.length > 0 {
+                remaining_files.push(extent);
+            }
+        }
+
+        remaining_files.extend(moved);
+        self.extents = remaining_files;
+        self.rebuild_free_spans();
+        moves
+    }
+
+    // Moves every file once, highest id first, to the leftmost free span before it that
+    // fits the whole file. When ids increase left to right the files are visited right to
+    // left, so the space a moved file leaves behind is never to the left of a file still
+    // waiting to move. Other disks, such as ones with explicit file ids, need that space
+    // merged back and go through move_whole_files.
+    fn compact_whole_files(&mut self) -> Vec<Move> {
+        let order = self.files_by_descending_id();
+        if !order
+            .windows(2)
+            .all(|pair| self.extents[pair[0]].start > self.extents[pair[1]].start)
+        {
+            return self.move_whole_files(&order, Fit::Leftmost);
+        }
+
+        let mut free_span_index = FreeSpanIndex::new(&self.free_spans);
+        let mut moves: Vec<Move> = Vec::new();
+
+        self.extents.sort_by_key(|extent| Reverse(extent.file.id));
+        for extent in self.extents.iter_mut() {
+            if let Some(start) = free_span_index.allocate(extent.length, extent.start) {
+                moves.push(Move {
+                    file: extent.file,
+                    from: extent.start,
+                    to: start,
+                    length: extent.length,
+                });
+                extent.start = start;
+            }
+        }
+
+        self.rebuild_free_spans();
+        moves
+    }
+
+    // Moves whole files in the given order of extent indices, each into a free span before
+    // it chosen by the fit policy. Unlike compact_whole_files the order is arbitrary, so the
+    // space a moved file leaves behind is merged back into the free spans.
+    fn move_whole_files(&mut self, order: &[usize], fit: Fit) -> Vec<Move> {
+        let mut free: BTreeMap<usize, usize> = self
+            .free_spans
             .iter()
-            .positions(|mem| *mem == Some(file))
+            .filter(|span| span.length > 0)
+            .map(|span| (span.start, span.length))
             .collect();
+        let mut moves: Vec<Move> = Vec::new();
 
-        let file_size = file_memory_indices.len();
-        let position_of_empty_slice = self
-            .memory
-            .windows(file_size)
-            .position(|memory_slice| memory_slice.iter().all(|mem| mem.is_none()));
-
-        match position_of_empty_slice {
-            Some(index) if index < file_memory_indices[0] => {
-                for relative_mem_index in 0..file_size {
-                    self.memory.swap(
-                        file_memory_indices[0] + relative_mem_index,
-                        index + relative_mem_index,
-                    );
-                }
+        for &index in order {
+            let extent = self.extents[index];
+            let mut candidates = free
+                .range(..extent.start)
+                .map(|(&start, &length)| (start, length))
+                .filter(|&(_, length)| length >= extent.length);
+            let chosen = match fit {
+                Fit::Leftmost => candidates.next(),
+                Fit::Best => candidates.min_by_key(|&(start, length)| (length, start)),
+                Fit::Worst => candidates.min_by_key(|&(start, length)| (Reverse(length), start)),
+            };
+            let Some((start, length)) = chosen else {
+                continue;
+            };
+
+            free.remove(&start);
+            if length > extent.length {
+                free.insert(start + extent.length, length - extent.length);
             }
-            _ => {}
+            release_span(&mut free, extent.start, extent.length);
+            self.extents[index].start = start;
+            moves.push(Move {
+                file: extent.file,
+                from: extent.start,
+                to: start,
+                length: extent.length,
+            });
         }
-        Ok(())
+
+        self.rebuild_free_spans();
+        moves
     }
 
-    fn calculate_checksum(&self) -> i64 {
-        self.memory
+    // Extent indices ordered by descending file id, the order of the puzzle.
+    fn files_by_descending_id(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.extents.len()).collect();
+        order.sort_by_key(|&index| Reverse(self.extents[index].file.id));
+        order
+    }
+
+    // Free spans with a file block after them, and the blocks they hold.
+    fn fragmentation(&self) -> (usize, usize) {
+        let last_file_end = self.extents.iter().map(Extent::end).max().unwrap_or(0);
+        self.free_spans
             .iter()
-            .enumerate()
-            .fold(0, |sum, (index, file_block)| match file_block {
-                Some(f) => sum + (f.id as i64) * (index as i64),
-                None => sum,
+            .filter(|span| span.start < last_file_end)
+            .fold((0, 0), |(gaps, gap_blocks), span| {
+                (gaps + 1, gap_blocks + span.length)
             })
     }
+
+    // The blocks of an extent hold consecutive positions, so each extent adds
+    // id * (start + ... + end - 1).
+    fn calculate_checksum(&self) -> Result<i64, ChecksumOverflow> {
+        self.extents.iter().try_fold(0, |sum, extent| {
+            let position_sum = (extent.start + extent.end() - 1) * extent.length / 2;
+            add_to_checksum(sum, extent.file.id, position_sum)
+        })
+    }
+}
+
+fn release_span(free: &mut BTreeMap<usize, usize>, mut start: usize, mut length: usize) {
+    if let Some((&previous_start, &previous_length)) = free.range(..start).next_back() {
+        if previous_start + previous_length == start {
+            free.remove(&previous_start);
+            start = previous_start;
+            length += previous_length;
+        }
+    }
+    if let Some(next_length) = free.remove(&(start + length)) {
+        length += next_length;
+    }
+    free.insert(start, length);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fit {
+    Leftmost,
+    Best,
+    Worst,
+}
+
+trait CompactionStrategy {
+    fn name(&self) -> &str;
+
+    // Returns the moves made, in order.
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move>;
+}
+
+struct BlockCompaction;
+
+impl CompactionStrategy for BlockCompaction {
+    fn name(&self) -> &str {
+        "block"
+    }
+
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move> {
+        disk.compact_blocks()
+    }
+}
+
+struct LeftmostFit;
+
+impl CompactionStrategy for LeftmostFit {
+    fn name(&self) -> &str {
+        "leftmost-fit"
+    }
+
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move> {
+        disk.compact_whole_files()
+    }
+}
+
+struct BestFit;
+
+impl CompactionStrategy for BestFit {
+    fn name(&self) -> &str {
+        "best-fit"
+    }
+
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move> {
+        let order = disk.files_by_descending_id();
+        disk.move_whole_files(&order, Fit::Best)
+    }
+}
+
+struct WorstFit;
+
+impl CompactionStrategy for WorstFit {
+    fn name(&self) -> &str {
+        "worst-fit"
+    }
+
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move> {
+        let order = disk.files_by_descending_id();
+        disk.move_whole_files(&order, Fit::Worst)
+    }
+}
+
+// Repeats leftmost-fit passes until a pass moves nothing. Every move takes a file further
+// left, so this always ends.
+struct IterateUntilFixpoint;
+
+impl CompactionStrategy for IterateUntilFixpoint {
+    fn name(&self) -> &str {
+        "iterate-until-fixpoint"
+    }
+
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move> {
+        let mut moves = disk.compact_whole_files();
+        loop {
+            let order = disk.files_by_descending_id();
+            let pass_moves = disk.move_whole_files(&order, Fit::Leftmost);
+            if pass_moves.is_empty() {
+                return moves;
+            }
+            moves.extend(pass_moves);
+        }
+    }
 }
 
-fn part1(mut file_system: FileSystem) -> Result<i64, Box<dyn Error>> {
-    while !file_system.is_fully_sorted() {
-        file_system.sort_last_file_block_to_first_empty()?;
+// Leftmost fit, but the largest files get to move first.
+struct SortBySize;
+
+impl CompactionStrategy for SortBySize {
+    fn name(&self) -> &str {
+        "sort-by-size"
     }
 
-    Ok(file_system.calculate_checksum())
+    fn compact(&self, disk: &mut ExtentDisk) -> Vec<Move> {
+        let mut order = disk.files_by_descending_id();
+        order.sort_by_key(|&index| Reverse(disk.extents[index].length));
+        disk.move_whole_files(&order, Fit::Leftmost)
+    }
 }
 
-fn part2(mut file_system: FileSystem) -> Result<i64, Box<dyn Error>> {
-    if let Some(max_file_id) = file_system
-        .memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompactionReport {
+    strategy: String,
+    checksum: i64,
+    moves: usize,
+    gaps: usize,
+    gap_blocks: usize,
+}
+
+fn compare_strategies(
+    disk: &ExtentDisk,
+    strategies: &[Box<dyn CompactionStrategy>],
+) -> Result<Vec<CompactionReport>, ChecksumOverflow> {
+    strategies
         .iter()
-        .filter_map(|mem| *mem)
-        .map(|file| file.id)
-        .max()
-    {
-        for file_index in (0..(max_file_id + 1)).rev() {
-            file_system.move_whole_file_to_first_empty(File { id: file_index })?;
+        .map(|strategy| {
+            let mut compacted = disk.clone();
+            let moves = strategy.compact(&mut compacted);
+            let (gaps, gap_blocks) = compacted.fragmentation();
+            Ok(CompactionReport {
+                strategy: strategy.name().to_string(),
+                checksum: compacted.calculate_checksum()?,
+                moves: moves.len(),
+                gaps,
+                gap_blocks,
+            })
+        })
+        .collect()
+}
+
+fn compaction_report_table(reports: &[CompactionReport]) -> String {
+    let mut table = format!(
+        "{:<24} {:>16} {:>8} {:>8} {:>10}\n",
+        "Strategy", "Checksum", "Moves", "Gaps", "Gap blocks"
+    );
+    for report in reports {
+        table.push_str(&format!(
+            "{:<24} {:>16} {:>8} {:>8} {:>10}\n",
+            report.strategy, report.checksum, report.moves, report.gaps, report.gap_blocks
+        ));
+    }
+    table
+}
+
+fn all_strategies() -> Vec<Box<dyn CompactionStrategy>> {
+    vec![
+        Box::new(BlockCompaction),
+        Box::new(LeftmostFit),
+        Box::new(BestFit),
+        Box::new(WorstFit),
+        Box::new(IterateUntilFixpoint),
+        Box::new(SortBySize),
+    ]
+}
+
+// Free spans bucketed by length, each bucket a min-heap of (start, length). The last bucket
+// holds every span at least LONGEST_BUCKET long, so for files up to that length the
+// leftmost fitting span is the smallest top among the buckets from the file length up.
+struct FreeSpanIndex {
+    buckets: Vec<BinaryHeap<Reverse<(usize, usize)>>>,
+}
+
+impl FreeSpanIndex {
+    const LONGEST_BUCKET: usize = 9;
+
+    fn new(free_spans: &[FreeSpan]) -> FreeSpanIndex {
+        let mut index = FreeSpanIndex {
+            buckets: vec![BinaryHeap::new(); FreeSpanIndex::LONGEST_BUCKET + 1],
+        };
+        for &span in free_spans {
+            index.insert(span);
         }
+        index
     }
 
-    Ok(file_system.calculate_checksum())
+    fn insert(&mut self, span: FreeSpan) {
+        if span.length > 0 {
+            self.buckets[span.length.min(FreeSpanIndex::LONGEST_BUCKET)]
+                .push(Reverse((span.start, span.length)));
+        }
+    }
+
+    // Takes `length` blocks from the start of the leftmost span that fits them and starts
+    // before `before`, returning where they start. The rest of the span stays free.
+    fn allocate(&mut self, length: usize, before: usize) -> Option<usize> {
+        let (start, span_length) = if length > FreeSpanIndex::LONGEST_BUCKET {
+            // Files this long never come out of a disk map, so a scan is good enough.
+            let last_bucket = &mut self.buckets[FreeSpanIndex::LONGEST_BUCKET];
+            let span = last_bucket
+                .iter()
+                .map(|&Reverse(span)| span)
+                .filter(|&(_, span_length)| span_length >= length)
+                .min()
+                .filter(|&(start, _)| start < before)?;
+            last_bucket.retain(|&Reverse(other)| other != span);
+            span
+        } else {
+            let bucket = (length.max(1)..=FreeSpanIndex::LONGEST_BUCKET)
+                .filter(|&bucket| {
+                    self.buckets[bucket]
+                        .peek()
+                        .is_some_and(|&Reverse((start, _))| start < before)
+                })
+                .min_by_key(|&bucket| self.buckets[bucket].peek().map(|&Reverse(span)| span))?;
+            let Reverse(span) = self.buckets[bucket].pop()?;
+            span
+        };
+
+        self.insert(FreeSpan {
+            start: start + length,
+            length: span_length - length,
+        });
+        Some(start)
+    }
+}
+
+fn hue_to_rgb(hue: u32) -> [u8; 3] {
+    let rising = ((hue % 60) * 255 / 60) as u8;
+    let falling = 255 - rising;
+    match hue / 60 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// A truecolor PNG whose image data is stored in uncompressed deflate blocks, which keeps
+// the encoder tiny at the cost of file size.
+fn encode_png(width: u32, height: u32, pixels: &[[u8; 3]]) -> Vec<u8> {
+    let mut scanlines: Vec<u8> = Vec::new();
+    for row in pixels.chunks(width as usize) {
+        scanlines.push(0);
+        scanlines.extend(row.iter().flatten());
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = scanlines.chunks(u16::MAX as usize).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        zlib.push((index + 1 == blocks.len()) as u8);
+        zlib.extend((block.len() as u16).to_le_bytes());
+        zlib.extend((!(block.len() as u16)).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(adler32(&scanlines).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    header.extend([8, 2, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    for (chunk_type, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", Vec::new())] {
+        png.extend((data.len() as u32).to_be_bytes());
+        let mut typed_data = chunk_type.to_vec();
+        typed_data.extend(data);
+        png.extend(&typed_data);
+        png.extend(crc32(&typed_data).to_be_bytes());
+    }
+    png
+}
+
+fn print_move_log(moves: &[Move]) {
+    for step in moves {
+        println!(
+            "file {} moved {} block(s) from {} to {}",
+            step.file.id, step.length, step.from, step.to
+        );
+    }
+    println!("{} moves", moves.len());
+}
+
+fn part1(file_system: FileSystem) -> Result<i64, Box<dyn Error>> {
+    let mut disk = ExtentDisk::from_file_system(&file_system);
+    disk.compact_blocks();
+    debug_assert_eq!(
+        disk.calculate_checksum(),
+        disk.to_file_system().calculate_checksum()
+    );
+
+    Ok(disk.calculate_checksum()?)
+}
+
+fn part2(file_system: FileSystem) -> Result<i64, Box<dyn Error>> {
+    let mut disk = ExtentDisk::from_file_system(&file_system);
+    disk.compact_whole_files();
+    debug_assert_eq!(
+        disk.calculate_checksum(),
+        disk.to_file_system().calculate_checksum()
+    );
+
+    Ok(disk.calculate_checksum()?)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let data = import_data()?;
 
-    let file_system = file_blocks_from_string(data);
+    let file_system = file_blocks_from_string(data)?;
+
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    // "strategies" compares all compaction strategies.
+    // "serialize <strategy>" prints the compacted disk as a disk map.
+    // "log <strategy>" prints the moves of a compaction strategy and renders small disks as
+    // text. "png <strategy> <before path> <after path>" draws the disk as PNG strips.
+    let strategy_named = |name: &str| {
+        all_strategies()
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+            .ok_or(format!("Unknown compaction strategy {name}"))
+    };
+    const LONGEST_TEXT_RENDERING: usize = 200;
+    const PNG_WIDTH: usize = 1024;
+    match arguments
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => {}
+        ["log", name] => {
+            let mut disk = ExtentDisk::from_file_system(&file_system);
+            let moves = strategy_named(name)?.compact(&mut disk);
+            print_move_log(&moves);
+            if disk.size <= LONGEST_TEXT_RENDERING {
+                println!("before: {}", file_system.render());
+                println!("after:  {}", disk.to_file_system().render());
+            }
+            return Ok(());
+        }
+        ["serialize", name] => {
+            let mut disk = ExtentDisk::from_file_system(&file_system);
+            strategy_named(name)?.compact(&mut disk);
+            println!("{}", disk.to_file_system().to_verified_disk_map()?);
+            return Ok(());
+        }
+        ["png", name, before_path, after_path] => {
+            let mut disk = ExtentDisk::from_file_system(&file_system);
+            strategy_named(name)?.compact(&mut disk);
+            std::fs::write(before_path, file_system.to_png(PNG_WIDTH))?;
+            std::fs::write(after_path, disk.to_file_system().to_png(PNG_WIDTH))?;
+            return Ok(());
+        }
+        ["strategies"] => {
+            let disk = ExtentDisk::from_file_system(&file_system);
+            print!(
+                "{}",
+                compaction_report_table(&compare_strategies(&disk, &all_strategies())?)
+            );
+            return Ok(());
+        }
+        _ => return Err(format!("Unknown arguments {arguments:?}").into()),
+    }
 
     let result_part1 = part1(file_system.clone())?;
     let result_part2 = part2(file_system.clone())?;
@@ -152,6 +877,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2024::random::SeededGenerator;
 
     fn get_test_data() -> String {
         "2333133121414131402".to_string()
@@ -161,7 +887,7 @@ mod tests {
     fn test_part1() -> Result<(), Box<dyn Error>> {
         let data_as_string = get_test_data();
 
-        let file_system = file_blocks_from_string(data_as_string);
+        let file_system = file_blocks_from_string(data_as_string)?;
 
         let result = part1(file_system)?;
 
@@ -173,7 +899,7 @@ mod tests {
     fn test_part2() -> Result<(), Box<dyn Error>> {
         let data_as_string = get_test_data();
 
-        let file_system = file_blocks_from_string(data_as_string);
+        let file_system = file_blocks_from_string(data_as_string)?;
 
         let result = part2(file_system)?;
 
@@ -181,4 +907,335 @@ mod tests {
         assert!(result == 2858);
         Ok(())
     }
+
+    #[test]
+    fn test_extent_disk_layouts() -> Result<(), Box<dyn Error>> {
+        let file_system = file_blocks_from_string(get_test_data())?;
+        let disk = ExtentDisk::from_file_system(&file_system);
+        assert_eq!(disk.extents.len(), 10);
+        assert_eq!(disk.free_spans.len(), 8);
+        assert_eq!(disk.to_file_system().memory, file_system.memory);
+        assert_eq!(disk.calculate_checksum(), file_system.calculate_checksum());
+
+        let mut blocks_compacted = disk.clone();
+        blocks_compacted.compact_blocks();
+        assert_eq!(
+            blocks_compacted.to_file_system().render(),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(
+            blocks_compacted.free_spans,
+            vec![FreeSpan {
+                start: 28,
+                length: 14
+            }]
+        );
+
+        let mut files_compacted = disk.clone();
+        files_compacted.compact_whole_files();
+        let compacted_file_system = files_compacted.to_file_system();
+        assert_eq!(
+            compacted_file_system.render(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!(
+            files_compacted.calculate_checksum(),
+            compacted_file_system.calculate_checksum()
+        );
+        Ok(())
+    }
+
+    // The original block-by-block whole-file compaction, kept as a reference.
+    fn compact_whole_files_by_scanning(file_system: &mut FileSystem) {
+        let max_file_id = file_system
+            .memory
+            .iter()
+            .flatten()
+            .map(|file| file.id)
+            .max()
+            .unwrap_or(0);
+        for id in (0..=max_file_id).rev() {
+            let file_memory_indices: Vec<usize> = (0..file_system.memory.len())
+                .filter(|&index| file_system.memory[index] == Some(File { id }))
+                .collect();
+            let Some(&first_index) = file_memory_indices.first() else {
+                continue;
+            };
+            let file_size = file_memory_indices.len();
+            let position_of_empty_slice = file_system
+                .memory
+                .windows(file_size)
+                .position(|memory_slice| memory_slice.iter().all(|mem| mem.is_none()));
+            if let Some(index) = position_of_empty_slice.filter(|&index| index < first_index) {
+                for offset in 0..file_size {
+                    file_system
+                        .memory
+                        .swap(first_index + offset, index + offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_free_span_index_matches_scanning() -> Result<(), Box<dyn Error>> {
+        let mut generator = SeededGenerator::new(7);
+        let disk_map: String = (0..3001)
+            .map(|index| {
+                let digit = generator.below(10);
+                // Files are never empty.
+                let digit = if index % 2 == 0 { digit.max(1) } else { digit };
+                char::from_digit(digit, 10).unwrap_or('1')
+            })
+            .collect();
+        let mut file_system = file_blocks_from_string(disk_map)?;
+
+        let mut disk = ExtentDisk::from_file_system(&file_system);
+        disk.compact_whole_files();
+        compact_whole_files_by_scanning(&mut file_system);
+        assert_eq!(disk.to_file_system().memory, file_system.memory);
+
+        let mut index = FreeSpanIndex::new(&[
+            FreeSpan {
+                start: 10,
+                length: 3,
+            },
+            FreeSpan {
+                start: 2,
+                length: 1,
+            },
+            FreeSpan {
+                start: 20,
+                length: 30,
+            },
+        ]);
+        assert_eq!(index.allocate(2, 100), Some(10));
+        assert_eq!(index.allocate(2, 100), Some(20));
+        assert_eq!(index.allocate(1, 100), Some(2));
+        assert_eq!(index.allocate(1, 5), None);
+        assert_eq!(index.allocate(12, 100), Some(22));
+        assert_eq!(index.allocate(1, 100), Some(12));
+        Ok(())
+    }
+
+    #[test]
+    fn test_whole_file_compaction_with_unordered_ids() -> Result<(), Box<dyn Error>> {
+        let mut file_system = file_blocks_from_string("13203\n0 9 5".to_string())?;
+
+        let mut disk = ExtentDisk::from_file_system(&file_system);
+        disk.compact_whole_files();
+        compact_whole_files_by_scanning(&mut file_system);
+        assert_eq!(disk.to_file_system().memory, file_system.memory);
+        assert_eq!(disk.to_file_system().render(), "099555...");
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction_strategies() -> Result<(), Box<dyn Error>> {
+        let file_system = file_blocks_from_string(get_test_data())?;
+        let disk = ExtentDisk::from_file_system(&file_system);
+        let reports = compare_strategies(&disk, &all_strategies())?;
+        let checksums: Vec<(&str, i64)> = reports
+            .iter()
+            .map(|report| (report.strategy.as_str(), report.checksum))
+            .collect();
+        assert_eq!(checksums[0], ("block", 1928));
+        assert_eq!(checksums[1], ("leftmost-fit", 2858));
+
+        let block = &reports[0];
+        assert_eq!((block.gaps, block.gap_blocks), (0, 0));
+        let leftmost_fit = &reports[1];
+        assert_eq!(leftmost_fit.moves, 4);
+        assert_eq!((leftmost_fit.gaps, leftmost_fit.gap_blocks), (5, 12));
+
+        // A pass that moves nothing can not change anything, so the fixpoint is at least
+        // as compact as a single leftmost-fit pass.
+        let fixpoint = &reports[4];
+        assert!(fixpoint.moves >= leftmost_fit.moves);
+        assert!(fixpoint.gap_blocks <= leftmost_fit.gap_blocks);
+
+        for strategy in all_strategies() {
+            let mut compacted = disk.clone();
+            strategy.compact(&mut compacted);
+            let mut blocks: Vec<File> = compacted
+                .to_file_system()
+                .memory
+                .into_iter()
+                .flatten()
+                .collect();
+            let mut original_blocks: Vec<File> =
+                file_system.memory.iter().flatten().copied().collect();
+            blocks.sort_by_key(|file| file.id);
+            original_blocks.sort_by_key(|file| file.id);
+            assert_eq!(blocks, original_blocks, "{} lost blocks", strategy.name());
+        }
+
+        // 0.....1...22: the three block span is the best fit for file 2, the five block span
+        // the worst. File 1 then goes to the leftmost span that is left before it.
+        let disk = ExtentDisk::from_file_system(&file_blocks_from_string("15132".to_string())?);
+        let mut best_fit = disk.clone();
+        assert_eq!(BestFit.compact(&mut best_fit).len(), 2);
+        assert_eq!(best_fit.to_file_system().render(), "01.....22...");
+        let mut worst_fit = disk.clone();
+        assert_eq!(WorstFit.compact(&mut worst_fit).len(), 2);
+        assert_eq!(worst_fit.to_file_system().render(), "0221........");
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_log_replays_compaction() -> Result<(), Box<dyn Error>> {
+        let file_system = file_blocks_from_string(get_test_data())?;
+        for strategy in all_strategies() {
+            let mut disk = ExtentDisk::from_file_system(&file_system);
+            let moves = strategy.compact(&mut disk);
+
+            let mut replayed = file_system.clone();
+            for step in &moves {
+                for offset in 0..step.length {
+                    assert_eq!(replayed.memory[step.from + offset], Some(step.file));
+                    assert_eq!(replayed.memory[step.to + offset], None);
+                    replayed.memory.swap(step.from + offset, step.to + offset);
+                }
+            }
+            assert_eq!(replayed.render(), disk.to_file_system().render());
+        }
+
+        let mut disk = ExtentDisk::from_file_system(&file_system);
+        let moves = LeftmostFit.compact(&mut disk);
+        assert_eq!(
+            moves[0],
+            Move {
+                file: File { id: 9 },
+                from: 40,
+                to: 2,
+                length: 2
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_strip() -> Result<(), Box<dyn Error>> {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let file_system = file_blocks_from_string(get_test_data())?;
+        let png = file_system.to_png(10);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes([png[16], png[17], png[18], png[19]]), 10);
+        assert_eq!(u32::from_be_bytes([png[20], png[21], png[22], png[23]]), 5);
+        assert_eq!(
+            &png[png.len() - 8..],
+            &[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_map_parsing() -> Result<(), Box<dyn Error>> {
+        let file_system = file_blocks_from_string(format!("  {}\r\n", get_test_data()))?;
+        assert_eq!(part1(file_system)?, 1928);
+
+        assert_eq!(
+            file_blocks_from_string("2333x33".to_string()).err(),
+            Some(DiskMapParseError::NotADigit {
+                position: 4,
+                character: 'x'
+            })
+        );
+        assert_eq!(
+            file_blocks_from_string("\n12 3".to_string()).err(),
+            Some(DiskMapParseError::NotADigit {
+                position: 3,
+                character: ' '
+            })
+        );
+        assert_eq!(
+            file_blocks_from_string("0312".to_string()).err(),
+            Some(DiskMapParseError::StartsWithFreeSpan)
+        );
+        assert_eq!(
+            file_blocks_from_string(" \n".to_string()).err(),
+            Some(DiskMapParseError::Empty)
+        );
+
+        // More files than fit in an i32 would take a 4GiB input, so check the id type and
+        // the checksum arithmetic directly.
+        let beyond_i32 = File {
+            id: i32::MAX as u64 + 10,
+        };
+        let file_system = FileSystem {
+            memory: vec![None, Some(beyond_i32)],
+        };
+        assert_eq!(file_system.calculate_checksum(), Ok(i32::MAX as i64 + 10));
+
+        let largest_id = File { id: u64::MAX };
+        let file_system = FileSystem {
+            memory: vec![None, Some(largest_id)],
+        };
+        assert_eq!(file_system.calculate_checksum(), Err(ChecksumOverflow));
+        let file_system = FileSystem {
+            memory: vec![
+                None,
+                None,
+                Some(File {
+                    id: i64::MAX as u64,
+                }),
+            ],
+        };
+        assert_eq!(file_system.calculate_checksum(), Err(ChecksumOverflow));
+        assert_eq!(
+            ExtentDisk::from_file_system(&file_system).calculate_checksum(),
+            Err(ChecksumOverflow)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_map_round_trip() -> Result<(), Box<dyn Error>> {
+        let file_system = file_blocks_from_string(get_test_data())?;
+        assert_eq!(file_system.to_verified_disk_map()?, get_test_data());
+
+        let mut disk = ExtentDisk::from_file_system(&file_system);
+        LeftmostFit.compact(&mut disk);
+        // 00992111777.44.333....5555.6666.....8888..
+        assert_eq!(
+            disk.to_file_system().to_verified_disk_map()?,
+            "20201030312134414542\n0 9 2 1 7 4 3 5 6 8"
+        );
+
+        for strategy in all_strategies() {
+            let mut disk = ExtentDisk::from_file_system(&file_system);
+            strategy.compact(&mut disk);
+            disk.to_file_system().to_verified_disk_map()?;
+        }
+
+        let long_runs = FileSystem {
+            memory: [
+                vec![Some(File { id: 0 }); 12],
+                vec![None; 20],
+                vec![Some(File { id: 1 }); 3],
+            ]
+            .concat(),
+        };
+        assert_eq!(long_runs.to_verified_disk_map()?, "903909023\n0 0 2 3 1");
+
+        assert!(FileSystem {
+            memory: vec![None, Some(File { id: 0 })]
+        }
+        .to_disk_map()
+        .is_err());
+        assert_eq!(
+            file_blocks_from_string("123\n0 x".to_string()).err(),
+            Some(DiskMapParseError::InvalidFileId("x".to_string()))
+        );
+        assert_eq!(
+            file_blocks_from_string("123\n5".to_string()).err(),
+            Some(DiskMapParseError::FileIdCountMismatch {
+                file_digits: 2,
+                file_ids: 1
+            })
+        );
+        Ok(())
+    }
 }
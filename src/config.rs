@@ -0,0 +1,37 @@
+// Parsing of the small "key = value" config files that some days accept.
+
+/// Splits a config into trimmed key and value pairs. Blank lines and lines starting with
+/// '#' are skipped. Fails on the first line that has no '=', naming its line number.
+pub fn key_value_pairs(config: &str) -> Result<Vec<(&str, &str)>, String> {
+    config
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or(format!(
+                    "Expected \"key = value\" on config line {line_number}, got \"{line}\""
+                ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_value_pairs() {
+        let config = "# comment\n\n  model = sides \ndiscount.A=10\n";
+        assert_eq!(
+            key_value_pairs(config),
+            Ok(vec![("model", "sides"), ("discount.A", "10")])
+        );
+        assert_eq!(
+            key_value_pairs("a = 1\n\nmissing"),
+            Err("Expected \"key = value\" on config line 3, got \"missing\"".to_string())
+        );
+    }
+}
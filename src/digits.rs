@@ -0,0 +1,79 @@
+// Decimal digit operations on integers, done arithmetically instead of through strings.
+
+/// Number of decimal digits, with 0 having one digit like "0".
+pub fn number_of_digits(value: u64) -> u32 {
+    value.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Splits after the first `position` digits, like `str::split_at` on the decimal string
+/// followed by parsing both halves. Leading zeros of the right half are dropped.
+/// Returns None when `value` has fewer than `position` digits.
+pub fn split_at_digit(value: u64, position: u32) -> Option<(u64, u64)> {
+    let digits = number_of_digits(value);
+    if position > digits {
+        return None;
+    }
+    if position == 0 {
+        return Some((0, value));
+    }
+    let divisor = 10_u64.pow(digits - position);
+    Some((value / divisor, value % divisor))
+}
+
+/// Writes the digits of `right` after the digits of `left`, so 12 and 345 give 12345.
+/// Returns None when the result does not fit in a u64.
+pub fn concatenate(left: u64, right: u64) -> Option<u64> {
+    left.checked_mul(10_u64.checked_pow(number_of_digits(right))?)?
+        .checked_add(right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every power of ten and its predecessor cover each digit count boundary, the rest
+    // have zeros in the middle or at the end.
+    fn sample_values() -> Vec<u64> {
+        let mut values = vec![0, 17, 253, 1000, 2024, 10_005, 123_456, 9_000_001, u64::MAX];
+        values.extend((0..20).map(|exponent| 10_u64.pow(exponent)));
+        values.extend((1..20).map(|exponent| 10_u64.pow(exponent) - 1));
+        values
+    }
+
+    #[test]
+    fn test_number_of_digits_matches_string_length() {
+        for value in sample_values() {
+            assert_eq!(number_of_digits(value) as usize, value.to_string().len());
+        }
+    }
+
+    #[test]
+    fn test_split_at_digit_matches_string_split() {
+        for value in sample_values() {
+            let string = value.to_string();
+            for position in 0..=string.len() {
+                let (left, right) = string.split_at(position);
+                let expected = (left.parse().unwrap_or(0), right.parse().unwrap_or(0));
+                assert_eq!(split_at_digit(value, position as u32), Some(expected));
+            }
+            assert_eq!(split_at_digit(value, string.len() as u32 + 1), None);
+        }
+    }
+
+    #[test]
+    fn test_concatenate_matches_string_concatenation() {
+        let values: Vec<u64> = sample_values()
+            .into_iter()
+            .filter(|&value| value < 1_000_000_000)
+            .collect();
+        for &left in &values {
+            for &right in values.iter().take(50) {
+                let expected: u64 = format!("{left}{right}").parse().unwrap();
+                assert_eq!(concatenate(left, right), Some(expected));
+            }
+        }
+        assert_eq!(concatenate(1, u64::MAX), None);
+        assert_eq!(concatenate(u64::MAX / 10, 9), None);
+        assert_eq!(concatenate(u64::MAX / 10, 5), Some(u64::MAX));
+    }
+}
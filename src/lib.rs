@@ -0,0 +1,3 @@
+pub mod config;
+pub mod digits;
+pub mod random;
@@ -0,0 +1,44 @@
+// Small deterministic pseudo-random numbers for reproducible randomised tests.
+
+/// Linear congruential generator with Knuth's MMIX constants. Not suitable for anything
+/// but test inputs.
+pub struct SeededGenerator {
+    state: u64,
+}
+
+impl SeededGenerator {
+    pub fn new(seed: u64) -> Self {
+        SeededGenerator { state: seed }
+    }
+
+    /// Advances the generator and returns the upper 32 bits of the new state, which are
+    /// the better distributed ones.
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 32) as u32
+    }
+
+    /// Returns a value in `0..bound`. The bound must not be 0.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        self.next_u32() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_gives_same_sequence() {
+        let mut first = SeededGenerator::new(42);
+        let mut second = SeededGenerator::new(42);
+        for _ in 0..100 {
+            assert_eq!(first.next_u32(), second.next_u32());
+        }
+        let mut generator = SeededGenerator::new(7);
+        assert!((0..1000).all(|_| generator.below(10) < 10));
+    }
+}